@@ -1,4 +1,5 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::too_many_arguments)]
 
 use ink_env::AccountId;
 use ink_env::Environment;
@@ -6,20 +7,99 @@ use ink_lang as ink;
 use ink_prelude::vec::Vec;
 use scale::{Decode, Encode};
 
+mod merkle;
+#[cfg(test)]
+mod mock;
 mod types;
 
 use types::*;
 
-#[derive(Encode, Decode, Debug)]
+#[derive(Encode, Decode, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum RmrkErrorCode {
     Failed,
 }
 
-#[derive(Encode, Decode)]
+#[derive(Encode, Decode, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub enum RmrkError {
     ErrorCode(RmrkErrorCode),
+    NftNotFound,
+    NotNftOwner,
+    NotDeposited,
+    AlreadyDeposited,
+    NftLocked,
+    NftInEscrow,
+    NotListed,
+    WrongPayment,
+    TransferFailed,
+    NotInAuction,
+    InvalidEndBlock,
+    AuctionEnded,
+    AuctionNotEnded,
+    BidTooLow,
+    HasBids,
+    InvalidPriceRange,
+    OfferNotFound,
+    OfferExpired,
+    OfferMismatch,
+    NotAuthorized,
+    SwapNotFound,
+    MintNotConfigured,
+    CollectionNotFound,
+    SupplyExceeded,
+    MintLimitExceeded,
+    InsufficientTreasury,
+    PhaseNotFound,
+    PhaseNotActive,
+    InvalidProof,
+    InvalidVoucher,
+    InvalidSignature,
+    VoucherExpired,
+    NonceUsed,
+    AlreadyCommitted,
+    NotCommitted,
+    InvalidSeed,
+    NotRevealed,
+    NoLootTable,
+    NoOpenRound,
+    BoxNotOpened,
+    RecipeNotFound,
+    RecipeDisabled,
+    InputsMismatch,
+    NotStakeable,
+    NotStaked,
+    InsufficientRewards,
+    NotForRent,
+    NftRented,
+    NotRented,
+    InvalidRentalPeriod,
+    RentalNotExpired,
+    ProposalNotFound,
+    VotingClosed,
+    VotingNotEnded,
+    AlreadyVoted,
+    AlreadyFinalized,
+    NotTransferable,
+    BadgeTypeNotFound,
+    NotBadge,
+    EventNotFound,
+    EventEnded,
+    NotTicket,
+    NotScanner,
+    AlreadyCheckedIn,
+    TicketImmutable,
+    ScanLimitExceeded,
+    NotGameServer,
+    InvalidLevels,
+    BundlesNotConfigured,
+    NotBundle,
+    EmptyBundle,
+    SaleNotStarted,
+    ResourceMismatch,
+    RefundNotDue,
+    InvalidRecipe,
+    AlreadyRevealed,
 }
 
 impl From<RmrkErrorCode> for RmrkError {
@@ -246,14 +326,556 @@ pub trait RmrkExt {
 #[ink::contract(env = crate::CustomEnvironment)]
 mod rmrk {
     use super::*;
+    use ink_env::hash::Blake2x256;
+    use ink_storage::{traits::SpreadAllocate, Mapping};
+
+    /// Upper bound of NFTs removed by a single `burn_nft` call, nested children included.
+    const MAX_BURNS: u32 = 32;
+
+    /// Upper bound of NFTs a query reads when it has to scan whole collections.
+    const MAX_SCAN: u32 = 512;
+
+    /// Blocks after opening a box within which its round's seed must be revealed, after which
+    /// the opener can take the box back.
+    const LOOT_REVEAL_TIMEOUT: BlockNumber = 14_400;
+
+    // Ticket property keys
+    const TICKET_SEAT: &[u8] = b"seat";
+    const TICKET_TIER: &[u8] = b"tier";
+    const TICKET_USED: &[u8] = b"used";
+
+    // Experience property keys
+    const XP: &[u8] = b"xp";
+    const LEVEL: &[u8] = b"level";
 
     #[ink(storage)]
-    pub struct RmrkTestContract {}
+    #[derive(SpreadAllocate)]
+    pub struct RmrkTestContract {
+        /// Account allowed to configure the contract and withdraw its treasury
+        admin: AccountId,
+        /// NFTs deposited with the contract account, keyed by the NFT
+        escrows: Mapping<(CollectionId, NftId), Escrow>,
+        /// Fixed-price listings of deposited NFTs
+        listings: Mapping<(CollectionId, NftId), Listing>,
+        /// English auctions of deposited NFTs
+        auctions: Mapping<(CollectionId, NftId), Auction>,
+        /// Outbid amounts not withdrawn yet
+        pending_returns: Mapping<AccountId, Balance>,
+        /// Descending-price auctions of deposited NFTs
+        dutch_auctions: Mapping<(CollectionId, NftId), DutchAuction>,
+        /// Escrowed bids on single NFTs or whole collections
+        offers: Mapping<OfferId, Offer>,
+        next_offer_id: OfferId,
+        /// Proposed peer-to-peer swaps
+        swaps: Mapping<SwapId, Swap>,
+        next_swap_id: SwapId,
+        /// Public mint settings per collection
+        mint_configs: Mapping<CollectionId, MintConfig>,
+        /// Number of NFTs each account minted through `public_mint`
+        public_minted: Mapping<(CollectionId, AccountId), u32>,
+        /// Mint proceeds the admin can withdraw
+        treasury: Balance,
+        /// Allowlist mint phases per collection
+        mint_phases: Mapping<(CollectionId, PhaseId), MintPhase>,
+        /// Number of NFTs each account claimed in a phase
+        phase_claimed: Mapping<(CollectionId, PhaseId, AccountId), u32>,
+        /// Ethereum-style address of the ECDSA key that signs mint vouchers
+        voucher_signer: Option<[u8; 20]>,
+        /// Voucher nonces already redeemed
+        used_nonces: Mapping<u64, ()>,
+        /// Commit-reveal state of blind drops
+        reveals: Mapping<CollectionId, Reveal>,
+        /// NFTs of blind drops that already got their real resource
+        revealed: Mapping<(CollectionId, NftId), ()>,
+        /// Rewards of each loot box collection
+        loot_tables: Mapping<CollectionId, LootTable>,
+        /// Seed commitments of each loot box collection, by round
+        loot_rounds: Mapping<(CollectionId, u32), LootRound>,
+        /// Number of seed rounds committed per loot box collection
+        loot_round_count: Mapping<CollectionId, u32>,
+        /// Burned boxes whose reward has not been claimed yet
+        opened_boxes: Mapping<(CollectionId, NftId), OpenedBox>,
+        /// Crafting recipes
+        recipes: Mapping<RecipeId, Recipe>,
+        next_recipe_id: RecipeId,
+        /// Reward per block for each staked NFT of a whitelisted collection
+        staking_rates: Mapping<CollectionId, Balance>,
+        /// Reward index of each collection, settled whenever its rate changes
+        reward_indices: Mapping<CollectionId, RewardIndex>,
+        stakes: Mapping<(CollectionId, NftId), Stake>,
+        /// Credited rewards not yet paid out
+        staking_rewards: Mapping<AccountId, Balance>,
+        /// Balance set aside for paying staking rewards
+        reward_pool: Balance,
+        rental_offers: Mapping<(CollectionId, NftId), RentalOffer>,
+        rentals: Mapping<(CollectionId, NftId), Rental>,
+        proposals: Mapping<ProposalId, Proposal>,
+        next_proposal_id: ProposalId,
+        /// NFTs that already voted on a proposal
+        proposal_votes: Mapping<(ProposalId, CollectionId, NftId), ()>,
+        /// Account each delegator lets vote with their NFTs
+        delegates: Mapping<AccountId, AccountId>,
+        badge_types: Mapping<BadgeTypeId, BadgeType>,
+        next_badge_type_id: BadgeTypeId,
+        /// Badge NFTs the contract holds on behalf of their holders
+        badges: Mapping<(CollectionId, NftId), Badge>,
+        account_badges: Mapping<AccountId, Vec<(CollectionId, NftId)>>,
+        events: Mapping<EventId, TicketedEvent>,
+        next_event_id: EventId,
+        /// Event each ticket admits to
+        tickets: Mapping<(CollectionId, NftId), EventId>,
+        /// Accounts allowed to check tickets in at an event
+        scanners: Mapping<(EventId, AccountId), ()>,
+        membership: Option<MembershipConfig>,
+        /// Accounts allowed to grant XP
+        game_servers: Mapping<AccountId, ()>,
+        /// Levels of a collection, ordered by XP
+        level_ups: Mapping<CollectionId, Vec<LevelUp>>,
+        resource_schedules: Mapping<CollectionId, ResourceSchedule>,
+        /// Collection bundle containers are minted into
+        bundle_collection: Option<CollectionId>,
+        /// Items nested into each bundle container
+        bundles: Mapping<(CollectionId, NftId), Vec<(CollectionId, NftId)>>,
+        /// Property keys copied when migrating NFTs out of a collection
+        migration_keys: Mapping<CollectionId, Vec<Vec<u8>>>,
+        /// New location of every migrated NFT
+        migrations: Mapping<(CollectionId, NftId), (CollectionId, NftId)>,
+    }
+
+    #[ink(event)]
+    pub struct Listed {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        seller: AccountId,
+        price: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Delisted {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct PriceUpdated {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        price: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Sold {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        buyer: AccountId,
+        seller: AccountId,
+        price: Balance,
+        royalty: Option<(AccountId, Balance)>,
+    }
+
+    #[ink(event)]
+    pub struct AuctionCreated {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        seller: AccountId,
+        reserve_price: Balance,
+        end_block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct BidPlaced {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        bidder: AccountId,
+        amount: Balance,
+        end_block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct AuctionSettled {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        winner: Option<AccountId>,
+        price: Balance,
+        royalty: Option<(AccountId, Balance)>,
+    }
+
+    #[ink(event)]
+    pub struct AuctionCancelled {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct DutchAuctionCreated {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        seller: AccountId,
+        start_price: Balance,
+        floor_price: Balance,
+        start_block: BlockNumber,
+        end_block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct OfferMade {
+        #[ink(topic)]
+        offer_id: OfferId,
+        #[ink(topic)]
+        buyer: AccountId,
+        target: OfferTarget,
+        amount: Balance,
+        expires_at: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct OfferCancelled {
+        #[ink(topic)]
+        offer_id: OfferId,
+    }
+
+    #[ink(event)]
+    pub struct OfferAccepted {
+        #[ink(topic)]
+        offer_id: OfferId,
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        seller: AccountId,
+        royalty: Option<(AccountId, Balance)>,
+    }
+
+    #[ink(event)]
+    pub struct SwapProposed {
+        #[ink(topic)]
+        swap_id: SwapId,
+        #[ink(topic)]
+        proposer: AccountId,
+        #[ink(topic)]
+        counterparty: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct SwapExecuted {
+        #[ink(topic)]
+        swap_id: SwapId,
+    }
+
+    #[ink(event)]
+    pub struct SwapCancelled {
+        #[ink(topic)]
+        swap_id: SwapId,
+    }
+
+    #[ink(event)]
+    pub struct PublicMinted {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        minter: AccountId,
+        first_nft_id: NftId,
+        quantity: u32,
+    }
+
+    #[ink(event)]
+    pub struct AllowlistMinted {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        phase_id: PhaseId,
+        #[ink(topic)]
+        minter: AccountId,
+        first_nft_id: NftId,
+        quantity: u32,
+    }
+
+    #[ink(event)]
+    pub struct VoucherRedeemed {
+        #[ink(topic)]
+        nonce: u64,
+        #[ink(topic)]
+        collection_id: CollectionId,
+        nft_id: NftId,
+        #[ink(topic)]
+        recipient: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProvenanceCommitted {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        provenance_hash: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct RevealSeedPublished {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        seed: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct NftRevealed {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        resource_id: ResourceId,
+    }
+
+    #[ink(event)]
+    pub struct BoxOpened {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        opener: AccountId,
+        round: u32,
+    }
+
+    #[ink(event)]
+    pub struct LootClaimed {
+        #[ink(topic)]
+        box_collection_id: CollectionId,
+        #[ink(topic)]
+        box_id: NftId,
+        #[ink(topic)]
+        reward_collection_id: CollectionId,
+        reward_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct BoxRefunded {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        opener: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Crafted {
+        #[ink(topic)]
+        recipe_id: RecipeId,
+        #[ink(topic)]
+        crafter: AccountId,
+        collection_id: CollectionId,
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct Staked {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Unstaked {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        #[ink(topic)]
+        owner: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Rented {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        #[ink(topic)]
+        renter: AccountId,
+        character: (CollectionId, NftId),
+        expires_at: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct RentalEnded {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        #[ink(topic)]
+        collection_id: CollectionId,
+        proposer: AccountId,
+        end_block: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct VoteCast {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        #[ink(topic)]
+        voter: AccountId,
+        support: bool,
+        weight: u32,
+    }
+
+    #[ink(event)]
+    pub struct DelegateChanged {
+        #[ink(topic)]
+        delegator: AccountId,
+        delegate: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct ProposalFinalized {
+        #[ink(topic)]
+        proposal_id: ProposalId,
+        passed: bool,
+        votes_for: u32,
+        votes_against: u32,
+    }
+
+    #[ink(event)]
+    pub struct BadgeAwarded {
+        #[ink(topic)]
+        badge_type: BadgeTypeId,
+        #[ink(topic)]
+        holder: AccountId,
+        collection_id: CollectionId,
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct BadgeRevoked {
+        #[ink(topic)]
+        badge_type: BadgeTypeId,
+        #[ink(topic)]
+        holder: AccountId,
+        collection_id: CollectionId,
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct TicketIssued {
+        #[ink(topic)]
+        event_id: EventId,
+        #[ink(topic)]
+        holder: AccountId,
+        collection_id: CollectionId,
+        nft_id: NftId,
+    }
+
+    #[ink(event)]
+    pub struct CheckedIn {
+        #[ink(topic)]
+        event_id: EventId,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        scanner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct XpGranted {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        amount: u64,
+        xp: u64,
+    }
+
+    #[ink(event)]
+    pub struct LeveledUp {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        level: u32,
+        resource_id: ResourceId,
+    }
+
+    #[ink(event)]
+    pub struct ScheduleApplied {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        nft_id: NftId,
+        resource_id: ResourceId,
+    }
+
+    #[ink(event)]
+    pub struct Bundled {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        container_id: NftId,
+        #[ink(topic)]
+        owner: AccountId,
+        items: Vec<(CollectionId, NftId)>,
+    }
+
+    #[ink(event)]
+    pub struct Unbundled {
+        #[ink(topic)]
+        collection_id: CollectionId,
+        #[ink(topic)]
+        container_id: NftId,
+        #[ink(topic)]
+        owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct NftMigrated {
+        #[ink(topic)]
+        from_collection: CollectionId,
+        from_nft_id: NftId,
+        #[ink(topic)]
+        to_collection: CollectionId,
+        to_nft_id: NftId,
+    }
 
     impl RmrkTestContract {
         #[ink(constructor)]
         pub fn new() -> Self {
-            RmrkTestContract {}
+            ink_lang::utils::initialize_contract(|contract: &mut Self| {
+                contract.admin = Self::env().caller();
+            })
         }
 
         // READ functions
@@ -364,6 +986,25 @@ mod rmrk {
             self.env().extension().lock(collection_id, nft_id)
         }
 
+        /// Returns the royalty recipient of an NFT and its share of `sale_price`.
+        ///
+        /// `RoyaltyInfo.amount` is applied as Permill and rounded down; `None` if the NFT does not
+        /// exist or has no royalty.
+        #[ink(message)]
+        pub fn royalty_info(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            sale_price: Balance,
+        ) -> Option<(AccountId, Balance)> {
+            let royalty = self
+                .env()
+                .extension()
+                .nfts(collection_id, nft_id)?
+                .royalty?;
+            Some((royalty.recipient, royalty.share_of(sale_price)))
+        }
+
         /// write functions
         #[ink(message)]
         pub fn mint_ntf(
@@ -376,6 +1017,8 @@ mod rmrk {
             transferable: bool,
             resources: Option<Vec<ResourceTypes>>,
         ) -> Result<(), RmrkError> {
+            // Minting as the contract skips the sale and allowlist checks
+            self.ensure_admin()?;
             self.env().extension().mint_nft(
                 owner,
                 collection_id,
@@ -398,6 +1041,7 @@ mod rmrk {
             transferable: bool,
             resources: Option<Vec<ResourceTypes>>,
         ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
             self.env().extension().mint_nft_directly_to_nft(
                 owner,
                 collection_id,
@@ -409,6 +1053,21 @@ mod rmrk {
             )
         }
 
+        /// Mints a whole tree of nested NFTs in one call.
+        ///
+        /// The root of `spec` is minted to `owner`, then every child is minted top-down directly
+        /// into the parent just created. Returns the ids of the minted NFTs in the shape of
+        /// `spec`; any failing mint reverts the whole call. Admin only.
+        #[ink(message)]
+        pub fn mint_tree(
+            &mut self,
+            owner: AccountId,
+            spec: MintTreeSpec,
+        ) -> Result<NftIdTree, RmrkError> {
+            self.ensure_admin()?;
+            self.mint_subtree(AccountIdOrCollectionNftTuple::AccountId(owner), spec)
+        }
+
         #[ink(message)]
         pub fn create_collection(
             &mut self,
@@ -428,6 +1087,8 @@ mod rmrk {
             nft_id: NftId,
             max_burns: u32,
         ) -> Result<(), RmrkError> {
+            self.ensure_not_escrowed(collection_id, nft_id)?;
+            self.ensure_ticket_mutable(collection_id, nft_id)?;
             self.env()
                 .extension()
                 .burn_nft(collection_id, nft_id, max_burns)
@@ -445,11 +1106,43 @@ mod rmrk {
             nft_id: NftId,
             new_owner: AccountIdOrCollectionNftTuple,
         ) -> Result<(), RmrkError> {
+            self.ensure_not_escrowed(collection_id, nft_id)?;
+            self.ensure_transferable(collection_id, nft_id)?;
             self.env()
                 .extension()
                 .send(collection_id, nft_id, new_owner)
         }
 
+        /// Sends several NFTs in one call.
+        ///
+        /// Only the admin can call this: every item must be root-owned by the contract, and like
+        /// `send` it refuses NFTs someone deposited and leaves the rest of the contract's NFTs to
+        /// the admin.
+        ///
+        /// All-or-nothing: every item is checked before anything is sent, and a `send` that
+        /// still fails reverts the whole call, so either every item moves or none does.
+        #[ink(message)]
+        pub fn send_batch(
+            &mut self,
+            items: Vec<(CollectionId, NftId, AccountIdOrCollectionNftTuple)>,
+        ) -> Result<(), RmrkError> {
+            for (collection_id, nft_id, _) in &items {
+                if self
+                    .root_owner(*collection_id, *nft_id)
+                    .ok_or(RmrkError::NftNotFound)?
+                    != self.env().account_id()
+                {
+                    return Err(RmrkError::NotNftOwner);
+                }
+                self.ensure_not_escrowed(*collection_id, *nft_id)?;
+                self.ensure_transferable(*collection_id, *nft_id)?;
+            }
+            for (collection_id, nft_id, new_owner) in items {
+                self.send(collection_id, nft_id, new_owner)?;
+            }
+            Ok(())
+        }
+
         #[ink(message)]
         pub fn accept_nft(
             &mut self,
@@ -462,6 +1155,23 @@ mod rmrk {
                 .accept_nft(collection_id, nft_id, new_owner)
         }
 
+        /// Accepts several pending NFTs in one call.
+        ///
+        /// All-or-nothing: the first failing `accept_nft` returns its error and the whole call is
+        /// reverted, so either every item is accepted or none is.
+        #[ink(message)]
+        pub fn accept_batch(
+            &mut self,
+            items: Vec<(CollectionId, NftId, AccountIdOrCollectionNftTuple)>,
+        ) -> Result<(), RmrkError> {
+            for (collection_id, nft_id, new_owner) in items {
+                self.env()
+                    .extension()
+                    .accept_nft(collection_id, nft_id, new_owner)?;
+            }
+            Ok(())
+        }
+
         #[ink(message)]
         pub fn reject_nft(
             &mut self,
@@ -477,6 +1187,7 @@ mod rmrk {
             collection_id: CollectionId,
             new_issuer: AccountId,
         ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
             self.env()
                 .extension()
                 .change_collection_issuer(collection_id, new_issuer)
@@ -490,6 +1201,26 @@ mod rmrk {
             key: Vec<u8>,
             value: Vec<u8>,
         ) -> Result<(), RmrkError> {
+            if let Some(nft_id) = maybe_nft_id {
+                self.ensure_ticket_mutable(collection_id, nft_id)?;
+                if [TICKET_SEAT, TICKET_TIER, TICKET_USED].contains(&key.as_slice())
+                    && self.tickets.contains((collection_id, nft_id))
+                {
+                    return Err(RmrkError::TicketImmutable);
+                }
+                // Only the admin assigns the properties membership tiers are based on
+                if self
+                    .membership
+                    .as_ref()
+                    .is_some_and(|config| config.uses_property(collection_id, &key))
+                {
+                    self.ensure_admin()?;
+                }
+                // Levels are only changed through `grant_xp` on collections that have them
+                if (key == XP || key == LEVEL) && self.level_ups.contains(collection_id) {
+                    return Err(RmrkError::NotAuthorized);
+                }
+            }
             self.env()
                 .extension()
                 .set_property(collection_id, maybe_nft_id, key, value)
@@ -507,6 +1238,8 @@ mod rmrk {
             nft_id: NftId,
             resource: BasicResource,
         ) -> Result<(), RmrkError> {
+            self.ensure_ticket_mutable(collection_id, nft_id)?;
+            self.ensure_not_leveled(collection_id)?;
             self.env()
                 .extension()
                 .add_basic_resource(collection_id, nft_id, resource)
@@ -519,6 +1252,8 @@ mod rmrk {
             nft_id: NftId,
             resource: ComposableResource,
         ) -> Result<(), RmrkError> {
+            self.ensure_ticket_mutable(collection_id, nft_id)?;
+            self.ensure_not_leveled(collection_id)?;
             self.env()
                 .extension()
                 .add_composable_resource(collection_id, nft_id, resource)
@@ -531,6 +1266,8 @@ mod rmrk {
             nft_id: NftId,
             resource: SlotResource,
         ) -> Result<(), RmrkError> {
+            self.ensure_ticket_mutable(collection_id, nft_id)?;
+            self.ensure_not_leveled(collection_id)?;
             self.env()
                 .extension()
                 .add_slot_resource(collection_id, nft_id, resource)
@@ -555,6 +1292,8 @@ mod rmrk {
             nft_id: NftId,
             resource_id: ResourceId,
         ) -> Result<(), RmrkError> {
+            self.ensure_ticket_mutable(collection_id, nft_id)?;
+            self.ensure_not_leveled(collection_id)?;
             self.env()
                 .extension()
                 .remove_resource(collection_id, nft_id, resource_id)
@@ -579,36 +1318,5346 @@ mod rmrk {
             nft_id: NftId,
             priorities: Vec<ResourceId>,
         ) -> Result<(), RmrkError> {
+            self.ensure_not_leveled(collection_id)?;
             self.env()
                 .extension()
                 .set_priority(collection_id, nft_id, priorities)
         }
     }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use ink_env::test;
-        // mod mock;
+    // Escrow
+    //
+    // The chain extension acts with the contract account as origin, so the contract can only move
+    // NFTs it owns. Owners register an NFT with `deposit_nft`, `send` it to the contract account
+    // through the pallet and call `confirm_deposit`, all in the same block (e.g. in one batched
+    // extrinsic); from then on the contract knows whom it belongs to. The pallet does not tell
+    // who sent an NFT, so binding the three steps to one block keeps a registration from being
+    // applied to an NFT that changed hands in between. Deposited NFTs cannot be moved or burned
+    // through the pass-through messages above, and neither can NFTs the contract holds without
+    // a confirmed deposit, except by the admin.
+    impl RmrkTestContract {
+        /// Registers the caller as depositor of an NFT they own directly, replacing any earlier
+        /// registration that was never confirmed.
+        #[ink(message)]
+        pub fn deposit_nft(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            if self.deposit_of(collection_id, nft_id).is_some() {
+                return Err(RmrkError::AlreadyDeposited);
+            }
+            self.ensure_transferable(collection_id, nft_id)?;
+            let owner = self.direct_owner(collection_id, nft_id)?;
+            if owner != Some(self.env().caller()) {
+                return Err(RmrkError::NotNftOwner);
+            }
+            self.escrows.insert(
+                (collection_id, nft_id),
+                &Escrow {
+                    depositor: self.env().caller(),
+                    locked: false,
+                    registered_at: self.env().block_number(),
+                    confirmed: false,
+                },
+            );
+            Ok(())
+        }
 
-        fn init_test_contract() -> RmrkTestContract {
-            let test_contract = RmrkTestContract::new();
+        /// Completes a deposit registered in this block once the NFT reached the contract.
+        #[ink(message)]
+        pub fn confirm_deposit(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let mut escrow = self
+                .escrows
+                .get((collection_id, nft_id))
+                .filter(|escrow| {
+                    !escrow.confirmed
+                        && escrow.registered_at == self.env().block_number()
+                        && self.holds(collection_id, nft_id)
+                })
+                .ok_or(RmrkError::NotDeposited)?;
+            if escrow.depositor != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            escrow.confirmed = true;
+            self.escrows.insert((collection_id, nft_id), &escrow);
+            Ok(())
+        }
 
-            // mock::register_chain_extension();
+        /// Returns a deposited NFT that no contract feature is using to its depositor.
+        #[ink(message)]
+        pub fn withdraw_nft(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let escrow = self
+                .escrows
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotDeposited)?;
+            if escrow.depositor != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            if escrow.locked {
+                return Err(RmrkError::NftLocked);
+            }
+            if !escrow.confirmed && self.holds(collection_id, nft_id) {
+                // The NFT reached the contract without the depositor confirming it in time
+                return Err(RmrkError::NotDeposited);
+            }
+            if self.holds(collection_id, nft_id) {
+                self.release_escrow(collection_id, nft_id, escrow.depositor)
+            } else {
+                self.escrows.remove((collection_id, nft_id));
+                Ok(())
+            }
+        }
 
-            test_contract
+        #[ink(message)]
+        pub fn escrow(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Escrow> {
+            self.escrows.get((collection_id, nft_id))
         }
+    }
 
-        fn accounts() -> test::DefaultAccounts<CustomEnvironment> {
-            test::default_accounts()
+    #[ink(impl)]
+    impl RmrkTestContract {
+        /// Returns the account directly owning an NFT, or `None` if it is nested in another NFT.
+        fn direct_owner(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<Option<AccountId>, RmrkError> {
+            let nft = self
+                .env()
+                .extension()
+                .nfts(collection_id, nft_id)
+                .ok_or(RmrkError::NftNotFound)?;
+            Ok(match nft.owner {
+                AccountIdOrCollectionNftTuple::AccountId(owner) => Some(owner),
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(..) => None,
+            })
         }
 
-        fn alice() -> AccountId {
-            accounts().alice
+        fn holds(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+            self.direct_owner(collection_id, nft_id) == Ok(Some(self.env().account_id()))
         }
 
-        fn bob() -> AccountId {
-            accounts().bob
+        /// Returns the confirmed deposit of an NFT the contract holds.
+        fn deposit_of(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Escrow> {
+            self.escrows
+                .get((collection_id, nft_id))
+                .filter(|escrow| escrow.confirmed && self.holds(collection_id, nft_id))
+        }
+
+        /// Refuses NFTs that are deposited, rented out or badges, and NFTs held by the contract
+        /// without a confirmed deposit unless the admin asks.
+        fn ensure_not_escrowed(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            if self.rentals.contains((collection_id, nft_id)) {
+                return Err(RmrkError::NftRented);
+            }
+            if self.badges.contains((collection_id, nft_id)) {
+                return Err(RmrkError::NotTransferable);
+            }
+            // NFTs nested into an escrowed NFT are held along with it
+            let mut top = (collection_id, nft_id);
+            while let Some(AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n)) = self
+                .env()
+                .extension()
+                .nfts(top.0, top.1)
+                .map(|nft| nft.owner)
+            {
+                top = (c, n);
+            }
+            if self.holds(top.0, top.1) {
+                if self.deposit_of(top.0, top.1).is_some() {
+                    return Err(RmrkError::NftInEscrow);
+                }
+                self.ensure_admin()?;
+            }
+            Ok(())
+        }
+
+        /// Refuses non-transferable NFTs and tickets that were already checked in.
+        fn ensure_transferable(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            if self
+                .env()
+                .extension()
+                .nfts(collection_id, nft_id)
+                .is_some_and(|nft| !nft.transferable)
+            {
+                return Err(RmrkError::NotTransferable);
+            }
+            if self.checked_in(collection_id, nft_id) {
+                return Err(RmrkError::AlreadyCheckedIn);
+            }
+            Ok(())
+        }
+
+        /// Locks an NFT the caller deposited so that it stays in escrow until released.
+        fn lock_escrow(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let mut escrow = self
+                .deposit_of(collection_id, nft_id)
+                .ok_or(RmrkError::NotDeposited)?;
+            if escrow.depositor != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            if escrow.locked {
+                return Err(RmrkError::NftLocked);
+            }
+            escrow.locked = true;
+            self.escrows.insert((collection_id, nft_id), &escrow);
+            Ok(())
+        }
+
+        /// Lets the depositor withdraw an escrowed NFT again.
+        fn unlock_escrow(&mut self, collection_id: CollectionId, nft_id: NftId) {
+            if let Some(mut escrow) = self.escrows.get((collection_id, nft_id)) {
+                escrow.locked = false;
+                self.escrows.insert((collection_id, nft_id), &escrow);
+            }
+        }
+
+        /// Burns an NFT the caller deposited and forgets its deposit.
+        fn burn_escrowed(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            self.lock_escrow(collection_id, nft_id)?;
+            self.escrows.remove((collection_id, nft_id));
+            self.env()
+                .extension()
+                .burn_nft(collection_id, nft_id, MAX_BURNS)?;
+            Ok(())
+        }
+
+        /// Sends an escrowed NFT to `to` and forgets its deposit.
+        fn release_escrow(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            to: AccountId,
+        ) -> Result<(), RmrkError> {
+            self.escrows.remove((collection_id, nft_id));
+            self.env().extension().send(
+                collection_id,
+                nft_id,
+                AccountIdOrCollectionNftTuple::AccountId(to),
+            )?;
+            Ok(())
+        }
+
+        fn pay(&self, to: AccountId, amount: Balance) -> Result<(), RmrkError> {
+            if amount == 0 {
+                return Ok(());
+            }
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| RmrkError::TransferFailed)
+        }
+
+        /// Hands an escrowed NFT to `buyer` and splits `price` between royalty recipient and
+        /// seller. Returns the royalty paid.
+        fn settle_sale(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            seller: AccountId,
+            buyer: AccountId,
+            price: Balance,
+        ) -> Result<Option<(AccountId, Balance)>, RmrkError> {
+            let royalty = self.royalty_info(collection_id, nft_id, price);
+            self.release_escrow(collection_id, nft_id, buyer)?;
+            let royalty_share = royalty.map_or(0, |(_, share)| share);
+            if let Some((recipient, share)) = royalty {
+                self.pay(recipient, share)?;
+            }
+            self.pay(seller, price - royalty_share)?;
+            Ok(royalty)
+        }
+
+        fn ensure_admin(&self) -> Result<(), RmrkError> {
+            if self.env().caller() != self.admin {
+                return Err(RmrkError::NotAuthorized);
+            }
+            Ok(())
+        }
+
+        /// Leaves the resources of collections with levels to `grant_xp` and the admin.
+        fn ensure_not_leveled(&self, collection_id: CollectionId) -> Result<(), RmrkError> {
+            if self.level_ups.contains(collection_id) {
+                self.ensure_admin()?;
+            }
+            Ok(())
+        }
+
+        /// Checks that the transferred value pays for `quantity` items at `price` and returns it.
+        fn charge(&self, price: Balance, quantity: u32) -> Result<Balance, RmrkError> {
+            let cost = price
+                .checked_mul(Balance::from(quantity))
+                .ok_or(RmrkError::WrongPayment)?;
+            if quantity == 0 || self.env().transferred_value() != cost {
+                return Err(RmrkError::WrongPayment);
+            }
+            Ok(cost)
+        }
+
+        /// Mints `quantity` NFTs from `config` to `owner` within the collection's supply cap.
+        /// Returns the id of the first minted NFT.
+        fn mint_drop(
+            &mut self,
+            collection_id: CollectionId,
+            owner: AccountId,
+            quantity: u32,
+            config: &MintConfig,
+        ) -> Result<NftId, RmrkError> {
+            let collection = self
+                .env()
+                .extension()
+                .collections(collection_id)
+                .ok_or(RmrkError::CollectionNotFound)?;
+            if let Some(max) = collection.max {
+                if collection.nfts_count.saturating_add(quantity) > max {
+                    return Err(RmrkError::SupplyExceeded);
+                }
+            }
+            let first_nft_id = self.env().extension().next_nft_id(collection_id);
+            for _ in 0..quantity {
+                self.env().extension().mint_nft(
+                    owner,
+                    collection_id,
+                    config.royalty_recipient,
+                    config.royalty,
+                    config.metadata.clone(),
+                    config.transferable,
+                    None,
+                )?;
+            }
+            Ok(first_nft_id)
+        }
+
+        /// Returns the current round of a loot box collection if its seed is still secret.
+        fn open_loot_round(&self, box_collection_id: CollectionId) -> Option<u32> {
+            let round = self
+                .loot_round_count
+                .get(box_collection_id)?
+                .checked_sub(1)?;
+            self.loot_rounds
+                .get((box_collection_id, round))
+                .filter(|loot_round| loot_round.seed.is_none())
+                .map(|_| round)
+        }
+
+        /// Returns the caller's stake on an NFT.
+        fn owned_stake(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<Stake, RmrkError> {
+            let stake = self
+                .stakes
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotStaked)?;
+            if stake.owner != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            Ok(stake)
+        }
+
+        /// Current reward index of a collection.
+        fn reward_index(&self, collection_id: CollectionId) -> Balance {
+            self.reward_indices
+                .get(collection_id)
+                .unwrap_or_default()
+                .at(
+                    self.staking_rates.get(collection_id).unwrap_or(0),
+                    self.env().block_number(),
+                )
+        }
+
+        /// Adds the rewards a stake accrued so far to its owner's claimable balance and moves
+        /// the stake up to the collection's current index.
+        fn credit_rewards(&mut self, collection_id: CollectionId, stake: &mut Stake) {
+            let reward_index = self.reward_index(collection_id);
+            let credited = self.staking_rewards.get(stake.owner).unwrap_or(0);
+            self.staking_rewards.insert(
+                stake.owner,
+                &credited.saturating_add(stake.accrued(reward_index)),
+            );
+            stake.reward_index = reward_index;
+        }
+
+        /// Returns an event that has not ended yet.
+        fn open_event(&self, event_id: EventId) -> Result<TicketedEvent, RmrkError> {
+            let event = self.events.get(event_id).ok_or(RmrkError::EventNotFound)?;
+            if self.env().block_number() >= event.ends_at {
+                return Err(RmrkError::EventEnded);
+            }
+            Ok(event)
+        }
+
+        fn checked_in(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+            self.tickets.contains((collection_id, nft_id))
+                && self
+                    .env()
+                    .extension()
+                    .properties(collection_id, Some(nft_id), TICKET_USED.to_vec())
+                    .is_some()
+        }
+
+        /// Refuses tickets of events that are over.
+        fn ensure_ticket_mutable(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            match self.tickets.get((collection_id, nft_id)) {
+                Some(event_id) if self.open_event(event_id).is_err() => {
+                    Err(RmrkError::TicketImmutable)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        /// Lists the NFTs of the membership collections that `account` root-owns.
+        fn root_owned_members(
+            &self,
+            collections: &[CollectionId],
+            account: AccountId,
+        ) -> Result<Vec<(CollectionId, NftId)>, RmrkError> {
+            let mut scanned = 0u32;
+            let mut owned = Vec::new();
+            for &collection_id in collections {
+                let minted = self.env().extension().next_nft_id(collection_id);
+                scanned = scanned.saturating_add(minted);
+                if scanned > MAX_SCAN {
+                    return Err(RmrkError::ScanLimitExceeded);
+                }
+                for nft_id in 0..minted {
+                    if self.root_owner(collection_id, nft_id) == Some(account) {
+                        owned.push((collection_id, nft_id));
+                    }
+                }
+            }
+            Ok(owned)
+        }
+
+        /// Reads a non-negative numeric property, treating missing or invalid values as zero.
+        fn stat(&self, collection_id: CollectionId, nft_id: NftId, key: &[u8]) -> u64 {
+            self.env()
+                .extension()
+                .properties(collection_id, Some(nft_id), key.to_vec())
+                .and_then(|value| decode_stat(&value))
+                .and_then(|value| u64::try_from(value).ok())
+                .unwrap_or(0)
+        }
+
+        /// Lists the direct children of an NFT that are equipped.
+        fn equipped_children(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<Vec<(CollectionId, NftId)>, RmrkError> {
+            let parent =
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(collection_id, nft_id);
+            let mut scanned = 0u32;
+            let mut children = Vec::new();
+            for c in 0..self.env().extension().collection_index() {
+                let minted = self.env().extension().next_nft_id(c);
+                scanned = scanned.saturating_add(minted);
+                if scanned > MAX_SCAN {
+                    return Err(RmrkError::ScanLimitExceeded);
+                }
+                for n in 0..minted {
+                    if self
+                        .env()
+                        .extension()
+                        .nfts(c, n)
+                        .is_some_and(|nft| nft.equipped && nft.owner == parent)
+                    {
+                        children.push((c, n));
+                    }
+                }
+            }
+            Ok(children)
+        }
+
+        fn migrate_nft(
+            &mut self,
+            from_collection: CollectionId,
+            to_collection: CollectionId,
+            nft_id: NftId,
+            keys: &[Vec<u8>],
+        ) -> Result<NftId, RmrkError> {
+            let nft = self
+                .env()
+                .extension()
+                .nfts(from_collection, nft_id)
+                .ok_or(RmrkError::NftNotFound)?;
+            let contract = self.env().account_id();
+            // Nothing the contract tracks may refer to the NFT or to an NFT it is nested in
+            let mut current = (from_collection, nft_id);
+            loop {
+                if self.rentals.contains(current) {
+                    return Err(RmrkError::NftRented);
+                }
+                if self.badges.contains(current) {
+                    return Err(RmrkError::NotTransferable);
+                }
+                if self.tickets.contains(current) {
+                    return Err(RmrkError::TicketImmutable);
+                }
+                if self.bundles.contains(current) {
+                    return Err(RmrkError::NftLocked);
+                }
+                if current != (from_collection, nft_id) && self.escrows.contains(current) {
+                    return Err(RmrkError::NftInEscrow);
+                }
+                match self
+                    .env()
+                    .extension()
+                    .nfts(current.0, current.1)
+                    .ok_or(RmrkError::NftNotFound)?
+                    .owner
+                {
+                    AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => current = (c, n),
+                    AccountIdOrCollectionNftTuple::AccountId(owner) if owner == contract => break,
+                    AccountIdOrCollectionNftTuple::AccountId(_) => {
+                        return Err(RmrkError::NotNftOwner)
+                    }
+                }
+            }
+            // Account the new NFT is handed to once it is set up
+            let mut depositor = None;
+            if let Some(escrow) = self.escrows.get((from_collection, nft_id)) {
+                if escrow.locked {
+                    return Err(RmrkError::NftLocked);
+                }
+                let escrow = self
+                    .deposit_of(from_collection, nft_id)
+                    .ok_or(RmrkError::NftInEscrow)?;
+                depositor = Some(escrow.depositor);
+            }
+
+            let (resource_ids, resources): (Vec<ResourceId>, Vec<ResourceTypes>) = (0..self
+                .env()
+                .extension()
+                .next_resource_id(from_collection, nft_id))
+                .filter_map(|resource_id| {
+                    let info =
+                        self.env()
+                            .extension()
+                            .resources(from_collection, nft_id, resource_id)?;
+                    Some((resource_id, info.resources))
+                })
+                .unzip();
+            // Resources are added in order, so an old resource's new id is its position
+            let priorities: Vec<ResourceId> = self
+                .resource_order(from_collection, nft_id)
+                .into_iter()
+                .filter_map(|old| resource_ids.iter().position(|id| *id == old))
+                .map(|new| new as ResourceId)
+                .collect();
+            let has_priorities = resource_ids.iter().any(|resource_id| {
+                self.env()
+                    .extension()
+                    .priorities(from_collection, nft_id, *resource_id)
+                    .is_some()
+            });
+            let properties: Vec<(Vec<u8>, Vec<u8>)> = keys
+                .iter()
+                .filter_map(|key| {
+                    let value = self.env().extension().properties(
+                        from_collection,
+                        Some(nft_id),
+                        key.clone(),
+                    )?;
+                    Some((key.clone(), value))
+                })
+                .collect();
+
+            // A budget of one burn makes the pallet refuse NFTs that still have children
+            self.env()
+                .extension()
+                .burn_nft(from_collection, nft_id, 1)?;
+            self.escrows.remove((from_collection, nft_id));
+            let new_id = self.env().extension().next_nft_id(to_collection);
+            let (royalty_recipient, royalty) = match nft.royalty {
+                Some(royalty) => (Some(royalty.recipient), Some(royalty.amount)),
+                None => (None, None),
+            };
+            match nft.owner {
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => {
+                    self.env().extension().mint_nft_directly_to_nft(
+                        (c, n),
+                        to_collection,
+                        royalty_recipient,
+                        royalty,
+                        nft.metadata,
+                        nft.transferable,
+                        Some(resources),
+                    )?
+                }
+                AccountIdOrCollectionNftTuple::AccountId(_) => self.env().extension().mint_nft(
+                    contract,
+                    to_collection,
+                    royalty_recipient,
+                    royalty,
+                    nft.metadata,
+                    nft.transferable,
+                    Some(resources),
+                )?,
+            }
+            if has_priorities {
+                self.env()
+                    .extension()
+                    .set_priority(to_collection, new_id, priorities)?;
+            }
+            for (key, value) in properties {
+                self.env()
+                    .extension()
+                    .set_property(to_collection, Some(new_id), key, value)?;
+            }
+            if let Some(depositor) = depositor {
+                self.env().extension().send(
+                    to_collection,
+                    new_id,
+                    AccountIdOrCollectionNftTuple::AccountId(depositor),
+                )?;
+            }
+            self.migrations
+                .insert((from_collection, nft_id), &(to_collection, new_id));
+            self.env().emit_event(NftMigrated {
+                from_collection,
+                from_nft_id: nft_id,
+                to_collection,
+                to_nft_id: new_id,
+            });
+            Ok(new_id)
+        }
+
+        fn seller_listing(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<Listing, RmrkError> {
+            let listing = self
+                .listings
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotListed)?;
+            if listing.seller != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            Ok(listing)
+        }
+
+        /// Follows the owner chain of an NFT up to the account at its root.
+        fn root_owner(&self, collection_id: CollectionId, nft_id: NftId) -> Option<AccountId> {
+            let mut current = (collection_id, nft_id);
+            loop {
+                match self.env().extension().nfts(current.0, current.1)?.owner {
+                    AccountIdOrCollectionNftTuple::AccountId(owner) => return Some(owner),
+                    AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => current = (c, n),
+                }
+            }
+        }
+
+        /// Adds a resource of any type to an NFT and returns its id.
+        fn add_resource(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            resource: ResourceTypes,
+        ) -> Result<ResourceId, RmrkError> {
+            let resource_id = self
+                .env()
+                .extension()
+                .next_resource_id(collection_id, nft_id);
+            match resource {
+                ResourceTypes::Basic(resource) => {
+                    self.env()
+                        .extension()
+                        .add_basic_resource(collection_id, nft_id, resource)?
+                }
+                ResourceTypes::Composable(resource) => self
+                    .env()
+                    .extension()
+                    .add_composable_resource(collection_id, nft_id, resource)?,
+                ResourceTypes::Slot(resource) => {
+                    self.env()
+                        .extension()
+                        .add_slot_resource(collection_id, nft_id, resource)?
+                }
+            }
+            Ok(resource_id)
+        }
+
+        /// Returns the resources of an NFT ordered by priority, unprioritised ones last.
+        fn resource_order(&self, collection_id: CollectionId, nft_id: NftId) -> Vec<ResourceId> {
+            let next_resource_id = self
+                .env()
+                .extension()
+                .next_resource_id(collection_id, nft_id);
+            let mut resources: Vec<(u32, ResourceId)> = (0..next_resource_id)
+                .filter(|resource_id| {
+                    self.env()
+                        .extension()
+                        .resources(collection_id, nft_id, *resource_id)
+                        .is_some()
+                })
+                .map(|resource_id| {
+                    let priority = self
+                        .env()
+                        .extension()
+                        .priorities(collection_id, nft_id, resource_id)
+                        .unwrap_or(u32::MAX);
+                    (priority, resource_id)
+                })
+                .collect();
+            resources.sort_unstable();
+            resources
+                .into_iter()
+                .map(|(_, resource_id)| resource_id)
+                .collect()
+        }
+
+        /// Moves a resource to the top of an NFT's priority list, keeping the others in order.
+        fn promote_resource(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            resource_id: ResourceId,
+        ) -> Result<(), RmrkError> {
+            let mut priorities = self.resource_order(collection_id, nft_id);
+            priorities.retain(|id| *id != resource_id);
+            priorities.insert(0, resource_id);
+            self.env()
+                .extension()
+                .set_priority(collection_id, nft_id, priorities)?;
+            Ok(())
+        }
+
+        fn mint_subtree(
+            &mut self,
+            owner: AccountIdOrCollectionNftTuple,
+            spec: MintTreeSpec,
+        ) -> Result<NftIdTree, RmrkError> {
+            let collection_id = spec.collection_id;
+            let nft_id = self.env().extension().next_nft_id(collection_id);
+            match owner {
+                AccountIdOrCollectionNftTuple::AccountId(owner) => {
+                    self.env().extension().mint_nft(
+                        owner,
+                        collection_id,
+                        spec.royalty_recipient,
+                        spec.royalty,
+                        spec.metadata,
+                        spec.transferable,
+                        spec.resources,
+                    )?
+                }
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(
+                    parent_collection,
+                    parent_nft,
+                ) => self.env().extension().mint_nft_directly_to_nft(
+                    (parent_collection, parent_nft),
+                    collection_id,
+                    spec.royalty_recipient,
+                    spec.royalty,
+                    spec.metadata,
+                    spec.transferable,
+                    spec.resources,
+                )?,
+            }
+
+            let mut children = Vec::with_capacity(spec.children.len());
+            for child in spec.children {
+                children.push(self.mint_subtree(
+                    AccountIdOrCollectionNftTuple::CollectionAndNftTuple(collection_id, nft_id),
+                    child,
+                )?);
+            }
+            Ok(NftIdTree {
+                collection_id,
+                nft_id,
+                children,
+            })
+        }
+    }
+
+    // Marketplace
+    impl RmrkTestContract {
+        /// Lists a deposited NFT for sale at a fixed price.
+        #[ink(message)]
+        pub fn list(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            price: Balance,
+        ) -> Result<(), RmrkError> {
+            self.lock_escrow(collection_id, nft_id)?;
+            let seller = self.env().caller();
+            self.listings
+                .insert((collection_id, nft_id), &Listing { seller, price });
+            self.env().emit_event(Listed {
+                collection_id,
+                nft_id,
+                seller,
+                price,
+            });
+            Ok(())
+        }
+
+        /// Removes a listing and returns the NFT to the seller.
+        #[ink(message)]
+        pub fn delist(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let listing = self.seller_listing(collection_id, nft_id)?;
+            self.listings.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, listing.seller)?;
+            self.env().emit_event(Delisted {
+                collection_id,
+                nft_id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn update_price(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            price: Balance,
+        ) -> Result<(), RmrkError> {
+            let mut listing = self.seller_listing(collection_id, nft_id)?;
+            listing.price = price;
+            self.listings.insert((collection_id, nft_id), &listing);
+            self.env().emit_event(PriceUpdated {
+                collection_id,
+                nft_id,
+                price,
+            });
+            Ok(())
+        }
+
+        /// Buys a listed NFT. The transferred value must equal the listing price.
+        ///
+        /// The royalty recipient from `nfts()` receives its share and the seller the rest.
+        #[ink(message, payable)]
+        pub fn buy(&mut self, collection_id: CollectionId, nft_id: NftId) -> Result<(), RmrkError> {
+            let listing = self
+                .listings
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotListed)?;
+            if self.env().transferred_value() != listing.price {
+                return Err(RmrkError::WrongPayment);
+            }
+            self.listings.remove((collection_id, nft_id));
+            let buyer = self.env().caller();
+            let royalty =
+                self.settle_sale(collection_id, nft_id, listing.seller, buyer, listing.price)?;
+            self.env().emit_event(Sold {
+                collection_id,
+                nft_id,
+                buyer,
+                seller: listing.seller,
+                price: listing.price,
+                royalty,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn listing(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Listing> {
+            self.listings.get((collection_id, nft_id))
+        }
+    }
+
+    // English auctions
+    impl RmrkTestContract {
+        /// Starts an auction of a deposited NFT that accepts bids until `end_block`.
+        #[ink(message)]
+        pub fn create_auction(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            reserve_price: Balance,
+            min_increment: Balance,
+            end_block: BlockNumber,
+            extension_window: BlockNumber,
+        ) -> Result<(), RmrkError> {
+            if end_block <= self.env().block_number() {
+                return Err(RmrkError::InvalidEndBlock);
+            }
+            self.lock_escrow(collection_id, nft_id)?;
+            let seller = self.env().caller();
+            self.auctions.insert(
+                (collection_id, nft_id),
+                &Auction {
+                    seller,
+                    reserve_price,
+                    min_increment,
+                    end_block,
+                    extension_window,
+                    highest_bid: None,
+                },
+            );
+            self.env().emit_event(AuctionCreated {
+                collection_id,
+                nft_id,
+                seller,
+                reserve_price,
+                end_block,
+            });
+            Ok(())
+        }
+
+        /// Bids the transferred value and credits the previous highest bid to its bidder, who
+        /// takes it out with `withdraw`.
+        ///
+        /// The first bid must reach the reserve price, later ones must beat the highest bid by at
+        /// least the minimum increment. A bid inside the extension window pushes the end back.
+        #[ink(message, payable)]
+        pub fn bid(&mut self, collection_id: CollectionId, nft_id: NftId) -> Result<(), RmrkError> {
+            let mut auction = self
+                .auctions
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotInAuction)?;
+            let now = self.env().block_number();
+            if now >= auction.end_block {
+                return Err(RmrkError::AuctionEnded);
+            }
+            let amount = self.env().transferred_value();
+            let minimum = match auction.highest_bid {
+                Some((_, highest)) => highest.saturating_add(auction.min_increment.max(1)),
+                None => auction.reserve_price,
+            };
+            if amount < minimum {
+                return Err(RmrkError::BidTooLow);
+            }
+
+            let bidder = self.env().caller();
+            if let Some((previous_bidder, previous_amount)) =
+                auction.highest_bid.replace((bidder, amount))
+            {
+                let pending = self.pending_returns.get(previous_bidder).unwrap_or(0);
+                self.pending_returns
+                    .insert(previous_bidder, &pending.saturating_add(previous_amount));
+            }
+            if auction.end_block - now < auction.extension_window {
+                auction.end_block = now.saturating_add(auction.extension_window);
+            }
+            self.auctions.insert((collection_id, nft_id), &auction);
+            self.env().emit_event(BidPlaced {
+                collection_id,
+                nft_id,
+                bidder,
+                amount,
+                end_block: auction.end_block,
+            });
+            Ok(())
+        }
+
+        /// Ends an auction after its end block. Anyone may call it.
+        ///
+        /// The NFT goes to the highest bidder and the bid is split between royalty recipient and
+        /// seller; without bids the NFT returns to the seller.
+        #[ink(message)]
+        pub fn settle_auction(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let auction = self
+                .auctions
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotInAuction)?;
+            if self.env().block_number() < auction.end_block {
+                return Err(RmrkError::AuctionNotEnded);
+            }
+            self.auctions.remove((collection_id, nft_id));
+            let (winner, price, royalty) = match auction.highest_bid {
+                Some((winner, price)) => {
+                    let royalty =
+                        self.settle_sale(collection_id, nft_id, auction.seller, winner, price)?;
+                    (Some(winner), price, royalty)
+                }
+                None => {
+                    self.release_escrow(collection_id, nft_id, auction.seller)?;
+                    (None, 0, None)
+                }
+            };
+            self.env().emit_event(AuctionSettled {
+                collection_id,
+                nft_id,
+                winner,
+                price,
+                royalty,
+            });
+            Ok(())
+        }
+
+        /// Cancels an auction without bids and returns the NFT to the seller.
+        #[ink(message)]
+        pub fn cancel_auction(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let auction = self
+                .auctions
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotInAuction)?;
+            if auction.seller != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            if auction.highest_bid.is_some() {
+                return Err(RmrkError::HasBids);
+            }
+            self.auctions.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, auction.seller)?;
+            self.env().emit_event(AuctionCancelled {
+                collection_id,
+                nft_id,
+            });
+            Ok(())
+        }
+
+        /// Pays out the caller's outbid amounts. Returns the amount paid.
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<Balance, RmrkError> {
+            let caller = self.env().caller();
+            let amount = self.pending_returns.get(caller).unwrap_or(0);
+            self.pending_returns.remove(caller);
+            self.pay(caller, amount)?;
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn pending_return(&self, account: AccountId) -> Balance {
+            self.pending_returns.get(account).unwrap_or(0)
+        }
+
+        #[ink(message)]
+        pub fn auction(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Auction> {
+            self.auctions.get((collection_id, nft_id))
+        }
+    }
+
+    // Dutch auctions
+    impl RmrkTestContract {
+        /// Starts a descending-price sale of a deposited NFT.
+        ///
+        /// The price falls from `start_price` at `start_block` to `floor_price` at `end_block`
+        /// and stays at the floor afterwards.
+        #[ink(message)]
+        pub fn create_dutch_auction(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            start_price: Balance,
+            floor_price: Balance,
+            start_block: BlockNumber,
+            end_block: BlockNumber,
+            decay: PriceDecay,
+        ) -> Result<(), RmrkError> {
+            if end_block <= start_block {
+                return Err(RmrkError::InvalidEndBlock);
+            }
+            if floor_price > start_price {
+                return Err(RmrkError::InvalidPriceRange);
+            }
+            self.lock_escrow(collection_id, nft_id)?;
+            let seller = self.env().caller();
+            self.dutch_auctions.insert(
+                (collection_id, nft_id),
+                &DutchAuction {
+                    seller,
+                    start_price,
+                    floor_price,
+                    start_block,
+                    end_block,
+                    decay,
+                },
+            );
+            self.env().emit_event(DutchAuctionCreated {
+                collection_id,
+                nft_id,
+                seller,
+                start_price,
+                floor_price,
+                start_block,
+                end_block,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn current_price(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Balance> {
+            self.dutch_auctions
+                .get((collection_id, nft_id))
+                .map(|auction| auction.price_at(self.env().block_number()))
+        }
+
+        /// Buys an NFT at its current Dutch auction price.
+        ///
+        /// The transferred value must be at least the current price; anything above it is
+        /// refunded.
+        #[ink(message, payable)]
+        pub fn buy_dutch(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let auction = self
+                .dutch_auctions
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotInAuction)?;
+            let price = auction.price_at(self.env().block_number());
+            let paid = self.env().transferred_value();
+            if paid < price {
+                return Err(RmrkError::BidTooLow);
+            }
+            self.dutch_auctions.remove((collection_id, nft_id));
+            let buyer = self.env().caller();
+            let royalty = self.settle_sale(collection_id, nft_id, auction.seller, buyer, price)?;
+            self.pay(buyer, paid - price)?;
+            self.env().emit_event(Sold {
+                collection_id,
+                nft_id,
+                buyer,
+                seller: auction.seller,
+                price,
+                royalty,
+            });
+            Ok(())
+        }
+
+        /// Cancels an unsold Dutch auction and returns the NFT to the seller.
+        #[ink(message)]
+        pub fn cancel_dutch_auction(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let auction = self
+                .dutch_auctions
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotInAuction)?;
+            if auction.seller != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            self.dutch_auctions.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, auction.seller)?;
+            self.env().emit_event(AuctionCancelled {
+                collection_id,
+                nft_id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn dutch_auction(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Option<DutchAuction> {
+            self.dutch_auctions.get((collection_id, nft_id))
+        }
+    }
+
+    // Offers
+    impl RmrkTestContract {
+        /// Offers the transferred value for an NFT or for any NFT of a collection.
+        ///
+        /// The value is held by the contract until the offer is accepted or cancelled.
+        #[ink(message, payable)]
+        pub fn make_offer(
+            &mut self,
+            target: OfferTarget,
+            expires_at: BlockNumber,
+        ) -> Result<OfferId, RmrkError> {
+            if expires_at <= self.env().block_number() {
+                return Err(RmrkError::InvalidEndBlock);
+            }
+            let amount = self.env().transferred_value();
+            if amount == 0 {
+                return Err(RmrkError::WrongPayment);
+            }
+            let offer_id = self.next_offer_id;
+            self.next_offer_id += 1;
+            let buyer = self.env().caller();
+            self.offers.insert(
+                offer_id,
+                &Offer {
+                    buyer,
+                    target: target.clone(),
+                    amount,
+                    expires_at,
+                },
+            );
+            self.env().emit_event(OfferMade {
+                offer_id,
+                buyer,
+                target,
+                amount,
+                expires_at,
+            });
+            Ok(offer_id)
+        }
+
+        /// Withdraws an offer and refunds the buyer.
+        ///
+        /// The buyer may cancel at any time, anyone else once the offer has expired.
+        #[ink(message)]
+        pub fn cancel_offer(&mut self, offer_id: OfferId) -> Result<(), RmrkError> {
+            let offer = self.offers.get(offer_id).ok_or(RmrkError::OfferNotFound)?;
+            if offer.buyer != self.env().caller() && self.env().block_number() < offer.expires_at {
+                return Err(RmrkError::NotAuthorized);
+            }
+            self.offers.remove(offer_id);
+            self.pay(offer.buyer, offer.amount)?;
+            self.env().emit_event(OfferCancelled { offer_id });
+            Ok(())
+        }
+
+        /// Sells a deposited NFT to the author of a matching offer.
+        ///
+        /// The NFT is sent to the buyer and the offered amount is split between royalty recipient
+        /// and the caller. A fixed-price listing of the NFT is cancelled first.
+        #[ink(message)]
+        pub fn accept_offer(
+            &mut self,
+            offer_id: OfferId,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let offer = self.offers.get(offer_id).ok_or(RmrkError::OfferNotFound)?;
+            if self.env().block_number() >= offer.expires_at {
+                return Err(RmrkError::OfferExpired);
+            }
+            if !offer.target.matches(collection_id, nft_id) {
+                return Err(RmrkError::OfferMismatch);
+            }
+            if self.listings.contains((collection_id, nft_id)) {
+                self.seller_listing(collection_id, nft_id)?;
+                self.listings.remove((collection_id, nft_id));
+                self.unlock_escrow(collection_id, nft_id);
+                self.env().emit_event(Delisted {
+                    collection_id,
+                    nft_id,
+                });
+            }
+            self.lock_escrow(collection_id, nft_id)?;
+            self.offers.remove(offer_id);
+            let seller = self.env().caller();
+            let royalty =
+                self.settle_sale(collection_id, nft_id, seller, offer.buyer, offer.amount)?;
+            self.env().emit_event(OfferAccepted {
+                offer_id,
+                collection_id,
+                nft_id,
+                seller,
+                royalty,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn offer(&self, offer_id: OfferId) -> Option<Offer> {
+            self.offers.get(offer_id)
+        }
+    }
+
+    // Swaps
+    impl RmrkTestContract {
+        /// Proposes to trade the caller's deposited `give` NFTs plus the transferred value for the
+        /// counterparty's `want` NFTs plus `want_balance`.
+        ///
+        /// The `give` NFTs stay locked in escrow until the swap is executed or cancelled.
+        #[ink(message, payable)]
+        pub fn propose_swap(
+            &mut self,
+            counterparty: AccountId,
+            give: Vec<(CollectionId, NftId)>,
+            want: Vec<(CollectionId, NftId)>,
+            want_balance: Balance,
+        ) -> Result<SwapId, RmrkError> {
+            for (collection_id, nft_id) in give.iter() {
+                self.lock_escrow(*collection_id, *nft_id)?;
+            }
+            let swap_id = self.next_swap_id;
+            self.next_swap_id += 1;
+            let proposer = self.env().caller();
+            self.swaps.insert(
+                swap_id,
+                &Swap {
+                    proposer,
+                    counterparty,
+                    give,
+                    want,
+                    give_balance: self.env().transferred_value(),
+                    want_balance,
+                },
+            );
+            self.env().emit_event(SwapProposed {
+                swap_id,
+                proposer,
+                counterparty,
+            });
+            Ok(swap_id)
+        }
+
+        /// Executes a swap as its counterparty, paying `want_balance` with the call.
+        ///
+        /// Every `want` NFT must have been deposited by the counterparty. All NFTs and balances
+        /// change hands in this call, or none do.
+        #[ink(message, payable)]
+        pub fn execute_swap(&mut self, swap_id: SwapId) -> Result<(), RmrkError> {
+            let swap = self.swaps.get(swap_id).ok_or(RmrkError::SwapNotFound)?;
+            if swap.counterparty != self.env().caller() {
+                return Err(RmrkError::NotAuthorized);
+            }
+            if self.env().transferred_value() != swap.want_balance {
+                return Err(RmrkError::WrongPayment);
+            }
+            for (collection_id, nft_id) in swap.want.iter() {
+                self.lock_escrow(*collection_id, *nft_id)?;
+            }
+            self.swaps.remove(swap_id);
+            for (collection_id, nft_id) in swap.give {
+                self.release_escrow(collection_id, nft_id, swap.counterparty)?;
+            }
+            for (collection_id, nft_id) in swap.want {
+                self.release_escrow(collection_id, nft_id, swap.proposer)?;
+            }
+            self.pay(swap.counterparty, swap.give_balance)?;
+            self.pay(swap.proposer, swap.want_balance)?;
+            self.env().emit_event(SwapExecuted { swap_id });
+            Ok(())
+        }
+
+        /// Cancels a swap as either party and returns the `give` NFTs and balance to the
+        /// proposer.
+        ///
+        /// `want` NFTs the counterparty deposited were never locked by the swap and stay
+        /// deposited until the counterparty withdraws them.
+        #[ink(message)]
+        pub fn cancel_swap(&mut self, swap_id: SwapId) -> Result<(), RmrkError> {
+            let swap = self.swaps.get(swap_id).ok_or(RmrkError::SwapNotFound)?;
+            let caller = self.env().caller();
+            if caller != swap.proposer && caller != swap.counterparty {
+                return Err(RmrkError::NotAuthorized);
+            }
+            self.swaps.remove(swap_id);
+            for (collection_id, nft_id) in swap.give {
+                self.release_escrow(collection_id, nft_id, swap.proposer)?;
+            }
+            self.pay(swap.proposer, swap.give_balance)?;
+            self.env().emit_event(SwapCancelled { swap_id });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn swap(&self, swap_id: SwapId) -> Option<Swap> {
+            self.swaps.get(swap_id)
+        }
+    }
+
+    // Admin
+    impl RmrkTestContract {
+        #[ink(message)]
+        pub fn admin(&self) -> AccountId {
+            self.admin
+        }
+
+        #[ink(message)]
+        pub fn set_admin(&mut self, new_admin: AccountId) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.admin = new_admin;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn treasury(&self) -> Balance {
+            self.treasury
+        }
+
+        /// Sends collected mint proceeds to `to`.
+        ///
+        /// Balance held for bids and offers is not part of the treasury and cannot be withdrawn.
+        #[ink(message)]
+        pub fn withdraw_treasury(
+            &mut self,
+            to: AccountId,
+            amount: Balance,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if amount > self.treasury {
+                return Err(RmrkError::InsufficientTreasury);
+            }
+            self.treasury -= amount;
+            self.pay(to, amount)
+        }
+    }
+
+    // Public mint
+    impl RmrkTestContract {
+        /// Configures the NFTs minted from a collection issued by the contract, or disables
+        /// minting with `None`.
+        ///
+        /// Allowlist phases and vouchers mint with this config whenever it is set; the public sale
+        /// only opens at `public_start_block`.
+        #[ink(message)]
+        pub fn set_mint_config(
+            &mut self,
+            collection_id: CollectionId,
+            config: Option<MintConfig>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            match config {
+                Some(config) => self.mint_configs.insert(collection_id, &config),
+                None => self.mint_configs.remove(collection_id),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn mint_config(&self, collection_id: CollectionId) -> Option<MintConfig> {
+            self.mint_configs.get(collection_id)
+        }
+
+        /// Mints `quantity` NFTs to the caller, who pays the configured price for each.
+        #[ink(message, payable)]
+        pub fn public_mint(
+            &mut self,
+            collection_id: CollectionId,
+            quantity: u32,
+        ) -> Result<(), RmrkError> {
+            let config = self
+                .mint_configs
+                .get(collection_id)
+                .ok_or(RmrkError::MintNotConfigured)?;
+            if config
+                .public_start_block
+                .is_none_or(|start| self.env().block_number() < start)
+            {
+                return Err(RmrkError::SaleNotStarted);
+            }
+            let cost = self.charge(config.price, quantity)?;
+
+            let minter = self.env().caller();
+            let minted = self.public_minted.get((collection_id, minter)).unwrap_or(0);
+            let minted = minted
+                .checked_add(quantity)
+                .filter(|minted| config.max_per_account.is_none_or(|max| *minted <= max))
+                .ok_or(RmrkError::MintLimitExceeded)?;
+            self.public_minted.insert((collection_id, minter), &minted);
+
+            let first_nft_id = self.mint_drop(collection_id, minter, quantity, &config)?;
+            self.treasury += cost;
+            self.env().emit_event(PublicMinted {
+                collection_id,
+                minter,
+                first_nft_id,
+                quantity,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn public_minted(&self, collection_id: CollectionId, account: AccountId) -> u32 {
+            self.public_minted
+                .get((collection_id, account))
+                .unwrap_or(0)
+        }
+    }
+
+    // Allowlist mint phases
+    impl RmrkTestContract {
+        /// Sets up an allowlist phase of a collection, or removes it with `None`.
+        #[ink(message)]
+        pub fn set_mint_phase(
+            &mut self,
+            collection_id: CollectionId,
+            phase_id: PhaseId,
+            phase: Option<MintPhase>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            match phase {
+                Some(phase) => {
+                    if phase.end_block <= phase.start_block {
+                        return Err(RmrkError::InvalidEndBlock);
+                    }
+                    self.mint_phases.insert((collection_id, phase_id), &phase)
+                }
+                None => self.mint_phases.remove((collection_id, phase_id)),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn mint_phase(
+            &self,
+            collection_id: CollectionId,
+            phase_id: PhaseId,
+        ) -> Option<MintPhase> {
+            self.mint_phases.get((collection_id, phase_id))
+        }
+
+        /// Mints `quantity` NFTs to an allowlisted caller during an active phase.
+        ///
+        /// `allowance` and `proof` must prove the caller's leaf (see `merkle.rs`) against the
+        /// phase root, and the caller may claim at most `allowance` NFTs in the phase. The phase
+        /// price is charged per NFT; metadata, royalty and transferability come from the
+        /// collection's mint config.
+        #[ink(message, payable)]
+        pub fn allowlist_mint(
+            &mut self,
+            collection_id: CollectionId,
+            phase_id: PhaseId,
+            quantity: u32,
+            allowance: u32,
+            proof: Vec<[u8; 32]>,
+        ) -> Result<(), RmrkError> {
+            let phase = self
+                .mint_phases
+                .get((collection_id, phase_id))
+                .ok_or(RmrkError::PhaseNotFound)?;
+            let now = self.env().block_number();
+            if now < phase.start_block || now >= phase.end_block {
+                return Err(RmrkError::PhaseNotActive);
+            }
+            let minter = self.env().caller();
+            if !merkle::verify(&phase.merkle_root, merkle::leaf(&minter, allowance), &proof) {
+                return Err(RmrkError::InvalidProof);
+            }
+            let claimed = self
+                .phase_claimed
+                .get((collection_id, phase_id, minter))
+                .unwrap_or(0)
+                .checked_add(quantity)
+                .filter(|claimed| *claimed <= allowance)
+                .ok_or(RmrkError::MintLimitExceeded)?;
+            let cost = self.charge(phase.price, quantity)?;
+            let config = self
+                .mint_configs
+                .get(collection_id)
+                .ok_or(RmrkError::MintNotConfigured)?;
+            self.phase_claimed
+                .insert((collection_id, phase_id, minter), &claimed);
+
+            let first_nft_id = self.mint_drop(collection_id, minter, quantity, &config)?;
+            self.treasury += cost;
+            self.env().emit_event(AllowlistMinted {
+                collection_id,
+                phase_id,
+                minter,
+                first_nft_id,
+                quantity,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn phase_claimed(
+            &self,
+            collection_id: CollectionId,
+            phase_id: PhaseId,
+            account: AccountId,
+        ) -> u32 {
+            self.phase_claimed
+                .get((collection_id, phase_id, account))
+                .unwrap_or(0)
+        }
+    }
+
+    // Mint vouchers
+    impl RmrkTestContract {
+        /// Sets the Ethereum-style address of the ECDSA key vouchers must be signed with, or
+        /// disables vouchers with `None`.
+        #[ink(message)]
+        pub fn set_voucher_signer(&mut self, signer: Option<[u8; 20]>) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.voucher_signer = signer;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn voucher_signer(&self) -> Option<[u8; 20]> {
+            self.voucher_signer
+        }
+
+        /// Mints the NFT described by a SCALE encoded `MintVoucher`. Anyone may relay a voucher.
+        ///
+        /// The signer signs the blake2-256 hash of this contract's 32-byte account id followed
+        /// directly by the encoded voucher bytes, without a length prefix, so a voucher is only
+        /// valid for one contract. Returns the minted id.
+        #[ink(message)]
+        pub fn redeem_voucher(
+            &mut self,
+            voucher: Vec<u8>,
+            signature: [u8; 65],
+        ) -> Result<NftId, RmrkError> {
+            let signer = self.voucher_signer.ok_or(RmrkError::InvalidSignature)?;
+            let mut message = AsRef::<[u8]>::as_ref(&self.env().account_id()).to_vec();
+            message.extend_from_slice(&voucher);
+            let mut message_hash = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&message, &mut message_hash);
+            let recovered = self
+                .env()
+                .ecdsa_recover(&signature, &message_hash)
+                .and_then(|public_key| self.env().ecdsa_to_eth_address(&public_key))
+                .map_err(|_| RmrkError::InvalidSignature)?;
+            if recovered != signer {
+                return Err(RmrkError::InvalidSignature);
+            }
+
+            let voucher =
+                MintVoucher::decode(&mut &voucher[..]).map_err(|_| RmrkError::InvalidVoucher)?;
+            if self.env().block_number() >= voucher.expiry {
+                return Err(RmrkError::VoucherExpired);
+            }
+            if self.used_nonces.contains(voucher.nonce) {
+                return Err(RmrkError::NonceUsed);
+            }
+            self.used_nonces.insert(voucher.nonce, &());
+
+            let nft_id = self.env().extension().next_nft_id(voucher.collection_id);
+            self.env().extension().mint_nft(
+                voucher.recipient,
+                voucher.collection_id,
+                None,
+                None,
+                voucher.metadata,
+                true,
+                voucher.resources,
+            )?;
+            self.env().emit_event(VoucherRedeemed {
+                nonce: voucher.nonce,
+                collection_id: voucher.collection_id,
+                nft_id,
+                recipient: voucher.recipient,
+            });
+            Ok(nft_id)
+        }
+
+        #[ink(message)]
+        pub fn nonce_used(&self, nonce: u64) -> bool {
+            self.used_nonces.contains(nonce)
+        }
+    }
+
+    // Delayed reveal
+    //
+    // The provenance hash commits to both the seed and the ordered list of final resources, so
+    // neither can change after minting. The seed only decides the rotation that maps NFT ids onto
+    // that list, and `reveal_batch` checks every resource against it.
+    impl RmrkTestContract {
+        /// Commits to the provenance hash that will reveal a blind drop.
+        #[ink(message)]
+        pub fn commit_provenance(
+            &mut self,
+            collection_id: CollectionId,
+            provenance_hash: [u8; 32],
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if self.reveals.contains(collection_id) {
+                return Err(RmrkError::AlreadyCommitted);
+            }
+            self.reveals.insert(
+                collection_id,
+                &Reveal {
+                    provenance_hash,
+                    seed: None,
+                    resource_hashes: Vec::new(),
+                    offset: 0,
+                },
+            );
+            self.env().emit_event(ProvenanceCommitted {
+                collection_id,
+                provenance_hash,
+            });
+            Ok(())
+        }
+
+        /// Publishes the seed and resource hashes matching the committed provenance hash, which
+        /// opens the reveal.
+        #[ink(message)]
+        pub fn publish_reveal_seed(
+            &mut self,
+            collection_id: CollectionId,
+            seed: Vec<u8>,
+            resource_hashes: Vec<[u8; 32]>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let mut reveal = self
+                .reveals
+                .get(collection_id)
+                .ok_or(RmrkError::NotCommitted)?;
+            if reveal.seed.is_some() {
+                return Err(RmrkError::AlreadyCommitted);
+            }
+            let mut provenance_hash = [0u8; 32];
+            ink_env::hash_encoded::<Blake2x256, _>(
+                &(&seed, &resource_hashes),
+                &mut provenance_hash,
+            );
+            if provenance_hash != reveal.provenance_hash || resource_hashes.is_empty() {
+                return Err(RmrkError::InvalidSeed);
+            }
+            let mut roll = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&seed, &mut roll);
+            let mut roll_bytes = [0u8; 4];
+            roll_bytes.copy_from_slice(&roll[..4]);
+            reveal.offset =
+                (u32::from_le_bytes(roll_bytes) as usize % resource_hashes.len()) as u32;
+            reveal.seed = Some(seed.clone());
+            reveal.resource_hashes = resource_hashes;
+            self.reveals.insert(collection_id, &reveal);
+            self.env().emit_event(RevealSeedPublished {
+                collection_id,
+                seed,
+            });
+            Ok(())
+        }
+
+        /// Adds the real resource to every NFT of the batch after the seed was published.
+        ///
+        /// Each resource must hash to the one the seed assigns to its NFT, and NFTs revealed
+        /// before fail with `AlreadyRevealed`. The resource is put on top of the priority list of
+        /// NFTs the contract root-owns. The pallet only lets the root owner reorder priorities, so
+        /// on other NFTs it is left pending for the owner to accept and prioritise.
+        #[ink(message)]
+        pub fn reveal_batch(
+            &mut self,
+            collection_id: CollectionId,
+            items: Vec<(NftId, ResourceTypes)>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let reveal = self
+                .reveals
+                .get(collection_id)
+                .filter(|reveal| reveal.seed.is_some())
+                .ok_or(RmrkError::NotRevealed)?;
+            for (index, (nft_id, resource)) in items.iter().enumerate() {
+                if self.revealed.contains((collection_id, *nft_id))
+                    || items[..index].iter().any(|(other, _)| other == nft_id)
+                {
+                    return Err(RmrkError::AlreadyRevealed);
+                }
+                let mut resource_hash = [0u8; 32];
+                ink_env::hash_encoded::<Blake2x256, _>(resource, &mut resource_hash);
+                if reveal.resource_hash(*nft_id) != Some(resource_hash) {
+                    return Err(RmrkError::ResourceMismatch);
+                }
+            }
+            for (nft_id, resource) in items {
+                self.revealed.insert((collection_id, nft_id), &());
+                let resource_id = self.add_resource(collection_id, nft_id, resource)?;
+                if self.root_owner(collection_id, nft_id) == Some(self.env().account_id()) {
+                    self.promote_resource(collection_id, nft_id, resource_id)?;
+                }
+                self.env().emit_event(NftRevealed {
+                    collection_id,
+                    nft_id,
+                    resource_id,
+                });
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn reveal(&self, collection_id: CollectionId) -> Option<Reveal> {
+            self.reveals.get(collection_id)
+        }
+
+        #[ink(message)]
+        pub fn revealed(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+            self.revealed.contains((collection_id, nft_id))
+        }
+    }
+
+    // Loot boxes
+    //
+    // Boxes are opened in rounds. The admin commits to a seed before a round starts, and opened
+    // boxes stay locked in escrow, recorded against the round with the block they were opened in.
+    // Once the admin reveals the seed, anyone can claim a box's reward, rolled from the seed and
+    // the opening block, which burns the box. Openers cannot predict the roll without the seed.
+    // The admin knows the seed up front and could predict the rewards of boxes it opens itself,
+    // but not pick when others open theirs. If the seed is withheld for `LOOT_REVEAL_TIMEOUT`
+    // blocks after an opening, the box can be refunded to its opener.
+    impl RmrkTestContract {
+        #[ink(message)]
+        pub fn set_loot_table(
+            &mut self,
+            box_collection_id: CollectionId,
+            table: LootTable,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.loot_tables.insert(box_collection_id, &table);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn loot_table(&self, box_collection_id: CollectionId) -> Option<LootTable> {
+            self.loot_tables.get(box_collection_id)
+        }
+
+        /// Starts a new round of box openings by committing to the blake2-256 hash of its seed.
+        ///
+        /// The previous round's seed must have been revealed.
+        #[ink(message)]
+        pub fn commit_loot_seed(
+            &mut self,
+            box_collection_id: CollectionId,
+            commitment: [u8; 32],
+        ) -> Result<u32, RmrkError> {
+            self.ensure_admin()?;
+            if self.open_loot_round(box_collection_id).is_some() {
+                return Err(RmrkError::AlreadyCommitted);
+            }
+            let round = self.loot_round_count.get(box_collection_id).unwrap_or(0);
+            self.loot_round_count
+                .insert(box_collection_id, &(round + 1));
+            self.loot_rounds.insert(
+                (box_collection_id, round),
+                &LootRound {
+                    commitment,
+                    seed: None,
+                },
+            );
+            Ok(round)
+        }
+
+        /// Reveals the seed of the current round, closing it.
+        #[ink(message)]
+        pub fn reveal_loot_seed(
+            &mut self,
+            box_collection_id: CollectionId,
+            seed: Vec<u8>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let round = self
+                .open_loot_round(box_collection_id)
+                .ok_or(RmrkError::NoOpenRound)?;
+            let mut loot_round = self
+                .loot_rounds
+                .get((box_collection_id, round))
+                .ok_or(RmrkError::NoOpenRound)?;
+            let mut seed_hash = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&seed, &mut seed_hash);
+            if seed_hash != loot_round.commitment {
+                return Err(RmrkError::InvalidSeed);
+            }
+            loot_round.seed = Some(seed);
+            self.loot_rounds
+                .insert((box_collection_id, round), &loot_round);
+            Ok(())
+        }
+
+        /// Locks a box the caller deposited and records it for the current round.
+        ///
+        /// With a `destination` NFT, which the caller must root-own, the reward is later minted
+        /// into it instead of the caller's account.
+        #[ink(message)]
+        pub fn open_box(
+            &mut self,
+            box_collection_id: CollectionId,
+            box_id: NftId,
+            destination: Option<(CollectionId, NftId)>,
+        ) -> Result<(), RmrkError> {
+            if !self.loot_tables.contains(box_collection_id) {
+                return Err(RmrkError::NoLootTable);
+            }
+            let round = self
+                .open_loot_round(box_collection_id)
+                .ok_or(RmrkError::NoOpenRound)?;
+            let opener = self.env().caller();
+            if let Some((collection_id, nft_id)) = destination {
+                if self.root_owner(collection_id, nft_id) != Some(opener) {
+                    return Err(RmrkError::NotNftOwner);
+                }
+            }
+            self.lock_escrow(box_collection_id, box_id)?;
+            self.opened_boxes.insert(
+                (box_collection_id, box_id),
+                &OpenedBox {
+                    opener,
+                    destination,
+                    round,
+                    opened_at: self.env().block_number(),
+                },
+            );
+            self.env().emit_event(BoxOpened {
+                collection_id: box_collection_id,
+                nft_id: box_id,
+                opener,
+                round,
+            });
+            Ok(())
+        }
+
+        /// Burns an opened box and mints its reward once its round's seed is revealed. Anyone may
+        /// call it. Returns the reward's collection and id.
+        #[ink(message)]
+        pub fn claim_loot(
+            &mut self,
+            box_collection_id: CollectionId,
+            box_id: NftId,
+        ) -> Result<(CollectionId, NftId), RmrkError> {
+            let opened = self
+                .opened_boxes
+                .get((box_collection_id, box_id))
+                .ok_or(RmrkError::BoxNotOpened)?;
+            let seed = self
+                .loot_rounds
+                .get((box_collection_id, opened.round))
+                .and_then(|round| round.seed)
+                .ok_or(RmrkError::NotRevealed)?;
+            let table = self
+                .loot_tables
+                .get(box_collection_id)
+                .ok_or(RmrkError::NoLootTable)?;
+            let mut roll = [0u8; 32];
+            ink_env::hash_encoded::<Blake2x256, _>(
+                &(&seed, box_collection_id, box_id, opened.opened_at),
+                &mut roll,
+            );
+            let mut roll_bytes = [0u8; 8];
+            roll_bytes.copy_from_slice(&roll[..8]);
+            let reward = table
+                .pick(u64::from_le_bytes(roll_bytes))
+                .ok_or(RmrkError::NoLootTable)?
+                .clone();
+
+            self.opened_boxes.remove((box_collection_id, box_id));
+            self.escrows.remove((box_collection_id, box_id));
+            self.env()
+                .extension()
+                .burn_nft(box_collection_id, box_id, MAX_BURNS)?;
+            let reward_id = self.env().extension().next_nft_id(reward.collection_id);
+            // The destination may have changed hands since the box was opened.
+            let destination = opened
+                .destination
+                .filter(|(c, n)| self.root_owner(*c, *n) == Some(opened.opener));
+            match destination {
+                Some(destination) => self.env().extension().mint_nft_directly_to_nft(
+                    destination,
+                    reward.collection_id,
+                    None,
+                    None,
+                    reward.metadata,
+                    reward.transferable,
+                    reward.resources,
+                )?,
+                None => self.env().extension().mint_nft(
+                    opened.opener,
+                    reward.collection_id,
+                    None,
+                    None,
+                    reward.metadata,
+                    reward.transferable,
+                    reward.resources,
+                )?,
+            }
+            self.env().emit_event(LootClaimed {
+                box_collection_id,
+                box_id,
+                reward_collection_id: reward.collection_id,
+                reward_id,
+            });
+            Ok((reward.collection_id, reward_id))
+        }
+
+        /// Returns an opened box to its opener when its round's seed was not revealed within
+        /// `LOOT_REVEAL_TIMEOUT` blocks of the opening. Anyone may call it.
+        #[ink(message)]
+        pub fn refund_box(
+            &mut self,
+            box_collection_id: CollectionId,
+            box_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let opened = self
+                .opened_boxes
+                .get((box_collection_id, box_id))
+                .ok_or(RmrkError::BoxNotOpened)?;
+            let revealed = self
+                .loot_rounds
+                .get((box_collection_id, opened.round))
+                .is_some_and(|round| round.seed.is_some());
+            if revealed
+                || self.env().block_number() < opened.opened_at.saturating_add(LOOT_REVEAL_TIMEOUT)
+            {
+                return Err(RmrkError::RefundNotDue);
+            }
+            self.opened_boxes.remove((box_collection_id, box_id));
+            self.release_escrow(box_collection_id, box_id, opened.opener)?;
+            self.env().emit_event(BoxRefunded {
+                collection_id: box_collection_id,
+                nft_id: box_id,
+                opener: opened.opener,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn opened_box(
+            &self,
+            box_collection_id: CollectionId,
+            box_id: NftId,
+        ) -> Option<OpenedBox> {
+            self.opened_boxes.get((box_collection_id, box_id))
+        }
+    }
+
+    // Crafting
+    impl RmrkTestContract {
+        /// Adds a recipe. Recipes that burn nothing or mint an NFT without metadata fail with
+        /// `InvalidRecipe`, here and in `update_recipe`.
+        #[ink(message)]
+        pub fn add_recipe(&mut self, recipe: Recipe) -> Result<RecipeId, RmrkError> {
+            self.ensure_admin()?;
+            if !recipe.is_valid() {
+                return Err(RmrkError::InvalidRecipe);
+            }
+            let recipe_id = self.next_recipe_id;
+            self.next_recipe_id += 1;
+            self.recipes.insert(recipe_id, &recipe);
+            Ok(recipe_id)
+        }
+
+        #[ink(message)]
+        pub fn update_recipe(
+            &mut self,
+            recipe_id: RecipeId,
+            recipe: Recipe,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if !self.recipes.contains(recipe_id) {
+                return Err(RmrkError::RecipeNotFound);
+            }
+            if !recipe.is_valid() {
+                return Err(RmrkError::InvalidRecipe);
+            }
+            self.recipes.insert(recipe_id, &recipe);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_recipe_enabled(
+            &mut self,
+            recipe_id: RecipeId,
+            enabled: bool,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let mut recipe = self
+                .recipes
+                .get(recipe_id)
+                .ok_or(RmrkError::RecipeNotFound)?;
+            recipe.enabled = enabled;
+            self.recipes.insert(recipe_id, &recipe);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn recipe(&self, recipe_id: RecipeId) -> Option<Recipe> {
+            self.recipes.get(recipe_id)
+        }
+
+        /// Burns the input NFTs and mints the recipe's output to the caller.
+        ///
+        /// Every input must have been deposited by the caller, which shows through `nfts()` that
+        /// the caller owned it and lets the contract burn it. Returns the crafted NFT's id.
+        #[ink(message)]
+        pub fn craft(
+            &mut self,
+            recipe_id: RecipeId,
+            inputs: Vec<(CollectionId, NftId)>,
+        ) -> Result<NftId, RmrkError> {
+            let recipe = self
+                .recipes
+                .get(recipe_id)
+                .ok_or(RmrkError::RecipeNotFound)?;
+            if !recipe.enabled {
+                return Err(RmrkError::RecipeDisabled);
+            }
+            if !recipe.accepts(&inputs) {
+                return Err(RmrkError::InputsMismatch);
+            }
+            for (collection_id, nft_id) in inputs {
+                self.burn_escrowed(collection_id, nft_id)?;
+            }
+
+            let crafter = self.env().caller();
+            let output = recipe.output;
+            let nft_id = self.env().extension().next_nft_id(output.collection_id);
+            self.env().extension().mint_nft(
+                crafter,
+                output.collection_id,
+                None,
+                None,
+                output.metadata,
+                output.transferable,
+                output.resources,
+            )?;
+            for (key, value) in output.properties {
+                self.env().extension().set_property(
+                    output.collection_id,
+                    Some(nft_id),
+                    key,
+                    value,
+                )?;
+            }
+            self.env().emit_event(Crafted {
+                recipe_id,
+                crafter,
+                collection_id: output.collection_id,
+                nft_id,
+            });
+            Ok(nft_id)
+        }
+    }
+
+    // Staking
+    impl RmrkTestContract {
+        /// Whitelists a collection for staking at `rate` per block and NFT, or removes it with
+        /// `None`.
+        ///
+        /// Rewards earned up to this block are settled at the previous rate, so a change only
+        /// applies from now on.
+        #[ink(message)]
+        pub fn set_staking_rate(
+            &mut self,
+            collection_id: CollectionId,
+            rate: Option<Balance>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.reward_indices.insert(
+                collection_id,
+                &RewardIndex {
+                    per_nft: self.reward_index(collection_id),
+                    updated_at: self.env().block_number(),
+                },
+            );
+            match rate {
+                Some(rate) => self.staking_rates.insert(collection_id, &rate),
+                None => self.staking_rates.remove(collection_id),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn staking_rate(&self, collection_id: CollectionId) -> Option<Balance> {
+            self.staking_rates.get(collection_id)
+        }
+
+        /// Adds the transferred value to the balance staking rewards are paid from.
+        #[ink(message, payable)]
+        pub fn fund_rewards(&mut self) -> Result<(), RmrkError> {
+            self.reward_pool += self.env().transferred_value();
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn reward_pool(&self) -> Balance {
+            self.reward_pool
+        }
+
+        /// Starts earning rewards on an NFT the caller deposited.
+        #[ink(message)]
+        pub fn stake(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            if !self.staking_rates.contains(collection_id) {
+                return Err(RmrkError::NotStakeable);
+            }
+            self.lock_escrow(collection_id, nft_id)?;
+            let owner = self.env().caller();
+            self.stakes.insert(
+                (collection_id, nft_id),
+                &Stake {
+                    owner,
+                    reward_index: self.reward_index(collection_id),
+                },
+            );
+            self.env().emit_event(Staked {
+                collection_id,
+                nft_id,
+                owner,
+            });
+            Ok(())
+        }
+
+        /// Credits the NFT's outstanding rewards to its owner and sends the NFT back.
+        #[ink(message)]
+        pub fn unstake(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let mut stake = self.owned_stake(collection_id, nft_id)?;
+            self.credit_rewards(collection_id, &mut stake);
+            self.stakes.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, stake.owner)?;
+            self.env().emit_event(Unstaked {
+                collection_id,
+                nft_id,
+                owner: stake.owner,
+            });
+            Ok(())
+        }
+
+        /// Credits the rewards of the caller's listed stakes and pays out everything credited
+        /// to the caller. Returns the amount paid.
+        #[ink(message)]
+        pub fn claim_rewards(
+            &mut self,
+            staked: Vec<(CollectionId, NftId)>,
+        ) -> Result<Balance, RmrkError> {
+            for (collection_id, nft_id) in staked {
+                let mut stake = self.owned_stake(collection_id, nft_id)?;
+                self.credit_rewards(collection_id, &mut stake);
+                self.stakes.insert((collection_id, nft_id), &stake);
+            }
+
+            let owner = self.env().caller();
+            let amount = self.staking_rewards.get(owner).unwrap_or(0);
+            if amount > self.reward_pool {
+                return Err(RmrkError::InsufficientRewards);
+            }
+            self.reward_pool -= amount;
+            self.staking_rewards.remove(owner);
+            self.pay(owner, amount)?;
+            self.env().emit_event(RewardsClaimed { owner, amount });
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn stake_info(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Stake> {
+            self.stakes.get((collection_id, nft_id))
+        }
+
+        /// Rewards credited to `owner` plus those still accruing on one of their stakes.
+        #[ink(message)]
+        pub fn pending_rewards(
+            &self,
+            owner: AccountId,
+            staked: Vec<(CollectionId, NftId)>,
+        ) -> Balance {
+            staked
+                .into_iter()
+                .filter_map(|(collection_id, nft_id)| {
+                    let stake = self
+                        .stakes
+                        .get((collection_id, nft_id))
+                        .filter(|stake| stake.owner == owner)?;
+                    Some(stake.accrued(self.reward_index(collection_id)))
+                })
+                .fold(
+                    self.staking_rewards.get(owner).unwrap_or(0),
+                    Balance::saturating_add,
+                )
+        }
+    }
+
+    // Rentals
+    //
+    // The RMRK pallet only lets the contract move NFTs it is root owner of, so a rented NFT is
+    // nested into a character the renter deposited rather than one the renter holds. Both stay
+    // locked in escrow until the rental ends, which keeps them from being burned or moved.
+    impl RmrkTestContract {
+        /// Offers a deposited NFT for rent at `price_per_block` for up to `max_blocks` blocks.
+        #[ink(message)]
+        pub fn offer_rental(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            price_per_block: Balance,
+            max_blocks: BlockNumber,
+        ) -> Result<(), RmrkError> {
+            if max_blocks == 0 {
+                return Err(RmrkError::InvalidRentalPeriod);
+            }
+            self.lock_escrow(collection_id, nft_id)?;
+            self.rental_offers.insert(
+                (collection_id, nft_id),
+                &RentalOffer {
+                    owner: self.env().caller(),
+                    price_per_block,
+                    max_blocks,
+                },
+            );
+            Ok(())
+        }
+
+        /// Withdraws a rental offer that is not currently rented and returns the NFT to its owner.
+        #[ink(message)]
+        pub fn cancel_rental_offer(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let offer = self
+                .rental_offers
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotForRent)?;
+            if offer.owner != self.env().caller() {
+                return Err(RmrkError::NotNftOwner);
+            }
+            if self.rentals.contains((collection_id, nft_id)) {
+                return Err(RmrkError::NftRented);
+            }
+            self.rental_offers.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, offer.owner)
+        }
+
+        /// Rents an NFT for `blocks` blocks and nests it into `character`, an NFT the caller
+        /// deposited. The transferred value must equal the price of the whole period and goes to
+        /// the owner.
+        #[ink(message, payable)]
+        pub fn rent(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            blocks: BlockNumber,
+            character: (CollectionId, NftId),
+        ) -> Result<(), RmrkError> {
+            let offer = self
+                .rental_offers
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotForRent)?;
+            if self.rentals.contains((collection_id, nft_id)) {
+                return Err(RmrkError::NftRented);
+            }
+            if blocks == 0 || blocks > offer.max_blocks {
+                return Err(RmrkError::InvalidRentalPeriod);
+            }
+            let price = offer
+                .price_per_block
+                .checked_mul(Balance::from(blocks))
+                .ok_or(RmrkError::WrongPayment)?;
+            if self.env().transferred_value() != price {
+                return Err(RmrkError::WrongPayment);
+            }
+            self.lock_escrow(character.0, character.1)?;
+
+            self.env().extension().send(
+                collection_id,
+                nft_id,
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(character.0, character.1),
+            )?;
+            let renter = self.env().caller();
+            let expires_at = self.env().block_number().saturating_add(blocks);
+            self.rentals.insert(
+                (collection_id, nft_id),
+                &Rental {
+                    renter,
+                    character,
+                    expires_at,
+                },
+            );
+            self.pay(offer.owner, price)?;
+            self.env().emit_event(Rented {
+                collection_id,
+                nft_id,
+                renter,
+                character,
+                expires_at,
+            });
+            Ok(())
+        }
+
+        /// Returns an expired rental to its owner and unlocks the renter's character. Anyone can
+        /// call this once the rental expired.
+        #[ink(message)]
+        pub fn end_rental(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let rental = self
+                .rentals
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotRented)?;
+            if self.env().block_number() < rental.expires_at {
+                return Err(RmrkError::RentalNotExpired);
+            }
+            let offer = self
+                .rental_offers
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotForRent)?;
+            self.rentals.remove((collection_id, nft_id));
+            self.rental_offers.remove((collection_id, nft_id));
+            self.release_escrow(collection_id, nft_id, offer.owner)?;
+            self.unlock_escrow(rental.character.0, rental.character.1);
+            self.env().emit_event(RentalEnded {
+                collection_id,
+                nft_id,
+                owner: offer.owner,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn rental_offer(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Option<RentalOffer> {
+            self.rental_offers.get((collection_id, nft_id))
+        }
+
+        #[ink(message)]
+        pub fn rental(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Rental> {
+            self.rentals.get((collection_id, nft_id))
+        }
+    }
+
+    // Governance
+    impl RmrkTestContract {
+        /// Opens a proposal that NFTs of `collection_id` can vote on for `voting_period` blocks.
+        #[ink(message)]
+        pub fn create_proposal(
+            &mut self,
+            collection_id: CollectionId,
+            description: Vec<u8>,
+            voting_period: BlockNumber,
+        ) -> Result<ProposalId, RmrkError> {
+            if voting_period == 0 {
+                return Err(RmrkError::InvalidEndBlock);
+            }
+            if self.env().extension().collections(collection_id).is_none() {
+                return Err(RmrkError::CollectionNotFound);
+            }
+            let proposal_id = self.next_proposal_id;
+            self.next_proposal_id += 1;
+            let proposer = self.env().caller();
+            let end_block = self.env().block_number().saturating_add(voting_period);
+            self.proposals.insert(
+                proposal_id,
+                &Proposal {
+                    proposer,
+                    collection_id,
+                    description,
+                    end_block,
+                    votes_for: 0,
+                    votes_against: 0,
+                    passed: None,
+                },
+            );
+            self.env().emit_event(ProposalCreated {
+                proposal_id,
+                collection_id,
+                proposer,
+                end_block,
+            });
+            Ok(proposal_id)
+        }
+
+        /// Lets `delegate` vote with the NFTs the caller root-owns, or takes the right back with
+        /// `None`. Delegation does not chain: a delegate only votes with NFTs its delegators
+        /// root-own themselves.
+        #[ink(message)]
+        pub fn delegate(&mut self, delegate: Option<AccountId>) -> Result<(), RmrkError> {
+            let delegator = self.env().caller();
+            match delegate {
+                Some(delegate) => self.delegates.insert(delegator, &delegate),
+                None => self.delegates.remove(delegator),
+            }
+            self.env().emit_event(DelegateChanged {
+                delegator,
+                delegate,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn delegate_of(&self, delegator: AccountId) -> Option<AccountId> {
+            self.delegates.get(delegator)
+        }
+
+        /// Votes with NFTs of the proposal's collection. Each NFT must be root-owned by the
+        /// caller, or by an account that delegated to the caller, and votes once per proposal.
+        /// Returns the weight of the vote.
+        #[ink(message)]
+        pub fn vote(
+            &mut self,
+            proposal_id: ProposalId,
+            nft_ids: Vec<NftId>,
+            support: bool,
+        ) -> Result<u32, RmrkError> {
+            let mut proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(RmrkError::ProposalNotFound)?;
+            if self.env().block_number() >= proposal.end_block {
+                return Err(RmrkError::VotingClosed);
+            }
+            let voter = self.env().caller();
+            for &nft_id in &nft_ids {
+                let owner = self
+                    .root_owner(proposal.collection_id, nft_id)
+                    .ok_or(RmrkError::NftNotFound)?;
+                let allowed = match self.delegates.get(owner) {
+                    Some(delegate) => delegate == voter,
+                    None => owner == voter,
+                };
+                if !allowed {
+                    return Err(RmrkError::NotNftOwner);
+                }
+                let key = (proposal_id, proposal.collection_id, nft_id);
+                if self.proposal_votes.contains(key) {
+                    return Err(RmrkError::AlreadyVoted);
+                }
+                self.proposal_votes.insert(key, &());
+            }
+
+            let weight = nft_ids.len() as u32;
+            if support {
+                proposal.votes_for += weight;
+            } else {
+                proposal.votes_against += weight;
+            }
+            self.proposals.insert(proposal_id, &proposal);
+            self.env().emit_event(VoteCast {
+                proposal_id,
+                voter,
+                support,
+                weight,
+            });
+            Ok(weight)
+        }
+
+        /// Records the outcome of a proposal whose voting period is over. A proposal passes with
+        /// more votes for than against. Anyone can call this.
+        #[ink(message)]
+        pub fn finalize_proposal(&mut self, proposal_id: ProposalId) -> Result<bool, RmrkError> {
+            let mut proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(RmrkError::ProposalNotFound)?;
+            if self.env().block_number() < proposal.end_block {
+                return Err(RmrkError::VotingNotEnded);
+            }
+            if proposal.passed.is_some() {
+                return Err(RmrkError::AlreadyFinalized);
+            }
+            let passed = proposal.votes_for > proposal.votes_against;
+            proposal.passed = Some(passed);
+            self.proposals.insert(proposal_id, &proposal);
+            self.env().emit_event(ProposalFinalized {
+                proposal_id,
+                passed,
+                votes_for: proposal.votes_for,
+                votes_against: proposal.votes_against,
+            });
+            Ok(passed)
+        }
+
+        #[ink(message)]
+        pub fn proposal(&self, proposal_id: ProposalId) -> Option<Proposal> {
+            self.proposals.get(proposal_id)
+        }
+
+        #[ink(message)]
+        pub fn has_voted(
+            &self,
+            proposal_id: ProposalId,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> bool {
+            self.proposal_votes
+                .contains((proposal_id, collection_id, nft_id))
+        }
+    }
+
+    // Badges
+    //
+    // The RMRK pallet only lets the contract burn NFTs it is root owner of, so badges are minted
+    // non-transferable to the contract itself and assigned to their holder here. That keeps them
+    // revocable, and `badges_of` lists what an account was awarded.
+    impl RmrkTestContract {
+        #[ink(message)]
+        pub fn define_badge_type(
+            &mut self,
+            collection_id: CollectionId,
+            metadata: Vec<u8>,
+        ) -> Result<BadgeTypeId, RmrkError> {
+            self.ensure_admin()?;
+            if self.env().extension().collections(collection_id).is_none() {
+                return Err(RmrkError::CollectionNotFound);
+            }
+            let badge_type = self.next_badge_type_id;
+            self.next_badge_type_id += 1;
+            self.badge_types.insert(
+                badge_type,
+                &BadgeType {
+                    collection_id,
+                    metadata,
+                },
+            );
+            Ok(badge_type)
+        }
+
+        #[ink(message)]
+        pub fn badge_type(&self, badge_type: BadgeTypeId) -> Option<BadgeType> {
+            self.badge_types.get(badge_type)
+        }
+
+        /// Mints a non-transferable badge of `badge_type` for `holder`. Returns its NFT id.
+        #[ink(message)]
+        pub fn award_badge(
+            &mut self,
+            badge_type: BadgeTypeId,
+            holder: AccountId,
+        ) -> Result<NftId, RmrkError> {
+            self.ensure_admin()?;
+            let BadgeType {
+                collection_id,
+                metadata,
+            } = self
+                .badge_types
+                .get(badge_type)
+                .ok_or(RmrkError::BadgeTypeNotFound)?;
+            let nft_id = self.env().extension().next_nft_id(collection_id);
+            self.env().extension().mint_nft(
+                self.env().account_id(),
+                collection_id,
+                None,
+                None,
+                metadata,
+                false,
+                None,
+            )?;
+            self.badges
+                .insert((collection_id, nft_id), &Badge { badge_type, holder });
+            let mut held = self.account_badges.get(holder).unwrap_or_default();
+            held.push((collection_id, nft_id));
+            self.account_badges.insert(holder, &held);
+            self.env().emit_event(BadgeAwarded {
+                badge_type,
+                holder,
+                collection_id,
+                nft_id,
+            });
+            Ok(nft_id)
+        }
+
+        /// Burns a badge and removes it from its holder's badges.
+        #[ink(message)]
+        pub fn revoke_badge(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let badge = self
+                .badges
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotBadge)?;
+            self.badges.remove((collection_id, nft_id));
+            let mut held = self.account_badges.get(badge.holder).unwrap_or_default();
+            held.retain(|id| *id != (collection_id, nft_id));
+            self.account_badges.insert(badge.holder, &held);
+            self.env()
+                .extension()
+                .burn_nft(collection_id, nft_id, MAX_BURNS)?;
+            self.env().emit_event(BadgeRevoked {
+                badge_type: badge.badge_type,
+                holder: badge.holder,
+                collection_id,
+                nft_id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn badge(&self, collection_id: CollectionId, nft_id: NftId) -> Option<Badge> {
+            self.badges.get((collection_id, nft_id))
+        }
+
+        /// Returns the badges awarded to `holder`, oldest first.
+        #[ink(message)]
+        pub fn badges_of(&self, holder: AccountId) -> Vec<(CollectionId, NftId)> {
+            self.account_badges.get(holder).unwrap_or_default()
+        }
+    }
+
+    // Tickets
+    //
+    // Tickets are ordinary NFTs owned by their holders. The contract refuses to move or trade a
+    // ticket once it is checked in, and to change a ticket through any of its messages once the
+    // event is over. A holder can still send a used ticket through the pallet directly, but the
+    // `used` property travels with it, so it cannot be checked in again. The seat, tier and `used`
+    // properties can only be written by the ticket messages.
+    impl RmrkTestContract {
+        /// Creates an event whose tickets are minted into `collection_id` and that ends at
+        /// `ends_at`.
+        #[ink(message)]
+        pub fn create_event(
+            &mut self,
+            collection_id: CollectionId,
+            ends_at: BlockNumber,
+        ) -> Result<EventId, RmrkError> {
+            self.ensure_admin()?;
+            if ends_at <= self.env().block_number() {
+                return Err(RmrkError::InvalidEndBlock);
+            }
+            if self.env().extension().collections(collection_id).is_none() {
+                return Err(RmrkError::CollectionNotFound);
+            }
+            let event_id = self.next_event_id;
+            self.next_event_id += 1;
+            self.events.insert(
+                event_id,
+                &TicketedEvent {
+                    collection_id,
+                    ends_at,
+                },
+            );
+            Ok(event_id)
+        }
+
+        #[ink(message)]
+        pub fn event(&self, event_id: EventId) -> Option<TicketedEvent> {
+            self.events.get(event_id)
+        }
+
+        /// Allows or disallows `scanner` to check tickets in at an event.
+        #[ink(message)]
+        pub fn set_scanner(
+            &mut self,
+            event_id: EventId,
+            scanner: AccountId,
+            allowed: bool,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if !self.events.contains(event_id) {
+                return Err(RmrkError::EventNotFound);
+            }
+            if allowed {
+                self.scanners.insert((event_id, scanner), &());
+            } else {
+                self.scanners.remove((event_id, scanner));
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_scanner(&self, event_id: EventId, scanner: AccountId) -> bool {
+            self.scanners.contains((event_id, scanner))
+        }
+
+        /// Mints a ticket for `holder` with its seat and tier stored as properties. Returns the
+        /// ticket's NFT id.
+        #[ink(message)]
+        pub fn issue_ticket(
+            &mut self,
+            event_id: EventId,
+            holder: AccountId,
+            seat: Vec<u8>,
+            tier: Vec<u8>,
+            metadata: Vec<u8>,
+        ) -> Result<NftId, RmrkError> {
+            self.ensure_admin()?;
+            let event = self.open_event(event_id)?;
+            let collection_id = event.collection_id;
+            let nft_id = self.env().extension().next_nft_id(collection_id);
+            self.env().extension().mint_nft(
+                holder,
+                collection_id,
+                None,
+                None,
+                metadata,
+                true,
+                None,
+            )?;
+            self.env().extension().set_property(
+                collection_id,
+                Some(nft_id),
+                TICKET_SEAT.to_vec(),
+                seat,
+            )?;
+            self.env().extension().set_property(
+                collection_id,
+                Some(nft_id),
+                TICKET_TIER.to_vec(),
+                tier,
+            )?;
+            self.tickets.insert((collection_id, nft_id), &event_id);
+            self.env().emit_event(TicketIssued {
+                event_id,
+                holder,
+                collection_id,
+                nft_id,
+            });
+            Ok(nft_id)
+        }
+
+        /// Marks a ticket as used. Only the event's scanners can check tickets in, and only
+        /// before the event ends.
+        #[ink(message)]
+        pub fn check_in(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let event_id = self
+                .tickets
+                .get((collection_id, nft_id))
+                .ok_or(RmrkError::NotTicket)?;
+            self.open_event(event_id)?;
+            let scanner = self.env().caller();
+            if !self.scanners.contains((event_id, scanner)) {
+                return Err(RmrkError::NotScanner);
+            }
+            if self.checked_in(collection_id, nft_id) {
+                return Err(RmrkError::AlreadyCheckedIn);
+            }
+            self.env().extension().set_property(
+                collection_id,
+                Some(nft_id),
+                TICKET_USED.to_vec(),
+                b"true".to_vec(),
+            )?;
+            self.env().emit_event(CheckedIn {
+                event_id,
+                collection_id,
+                nft_id,
+                scanner,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn ticket_event(&self, collection_id: CollectionId, nft_id: NftId) -> Option<EventId> {
+            self.tickets.get((collection_id, nft_id))
+        }
+
+        #[ink(message)]
+        pub fn is_checked_in(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+            self.checked_in(collection_id, nft_id)
+        }
+    }
+
+    // Membership tiers
+    impl RmrkTestContract {
+        /// Sets the membership collections and tiers, or removes all tiers with `None`.
+        ///
+        /// While set, only the admin can change the properties the tiers are based on through
+        /// `set_property`.
+        #[ink(message)]
+        pub fn set_membership(
+            &mut self,
+            config: Option<MembershipConfig>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.membership = config;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn membership(&self) -> Option<MembershipConfig> {
+            self.membership.clone()
+        }
+
+        /// Returns the index of the highest tier `account` qualifies for, or `None` if it
+        /// qualifies for none.
+        ///
+        /// Counts the NFTs of the membership collections that `account` root-owns, nested ones
+        /// included. The RMRK chain extension cannot list an account's NFTs, so every NFT minted
+        /// into those collections is read; past `MAX_SCAN` NFTs this fails with
+        /// `ScanLimitExceeded`. Other contracts can call this message to gate their features.
+        #[ink(message)]
+        pub fn membership_tier(&self, account: AccountId) -> Result<Option<u32>, RmrkError> {
+            let config = match &self.membership {
+                Some(config) => config,
+                None => return Ok(None),
+            };
+            let owned = self.root_owned_members(&config.collections, account)?;
+            let qualifying = |requirement: &TierRequirement| match requirement {
+                TierRequirement::Count(count) => owned.len() as u32 >= *count,
+                TierRequirement::Property { key, value, count } => {
+                    owned
+                        .iter()
+                        .filter(|(collection_id, nft_id)| {
+                            self.env()
+                                .extension()
+                                .properties(*collection_id, Some(*nft_id), key.clone())
+                                .as_ref()
+                                == Some(value)
+                        })
+                        .count() as u32
+                        >= *count
+                }
+            };
+            Ok(config
+                .tiers
+                .iter()
+                .rposition(|tier| qualifying(&tier.requirement))
+                .map(|tier| tier as u32))
+        }
+    }
+
+    // Stats
+    impl RmrkTestContract {
+        /// Sums the numeric properties `keys` of an NFT and of its equipped children. Returns one
+        /// total per key, in the order of `keys`.
+        ///
+        /// Property values are decoded with `decode_stat`: ASCII decimal integers with an
+        /// optional leading `-`. Missing or non-numeric values add nothing, and totals saturate
+        /// at the `i64` bounds. The RMRK chain extension cannot list an NFT's children, so every
+        /// NFT is read to find them; past `MAX_SCAN` NFTs this fails with `ScanLimitExceeded`.
+        #[ink(message)]
+        pub fn aggregate_stats(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            keys: Vec<Vec<u8>>,
+        ) -> Result<Vec<i64>, RmrkError> {
+            if self.env().extension().nfts(collection_id, nft_id).is_none() {
+                return Err(RmrkError::NftNotFound);
+            }
+            let mut sources = self.equipped_children(collection_id, nft_id)?;
+            sources.insert(0, (collection_id, nft_id));
+            Ok(keys
+                .into_iter()
+                .map(|key| {
+                    sources
+                        .iter()
+                        .filter_map(|(c, n)| {
+                            let value =
+                                self.env()
+                                    .extension()
+                                    .properties(*c, Some(*n), key.clone())?;
+                            decode_stat(&value)
+                        })
+                        .fold(0i64, i64::saturating_add)
+                })
+                .collect())
+        }
+    }
+
+    // Experience
+    //
+    // XP and level are kept as `xp` and `level` properties in the format `decode_stat` reads.
+    // Each level reached adds its resource, which goes on top of the priority list when the
+    // contract root-owns the NFT. The pallet only lets the root owner accept resources and
+    // reorder priorities, so on other NFTs the resource is left pending for the owner. Neither
+    // property can be set through `set_property` on a collection with levels, and only the
+    // admin can change its resources through the pass-through messages.
+    impl RmrkTestContract {
+        #[ink(message)]
+        pub fn set_game_server(
+            &mut self,
+            server: AccountId,
+            allowed: bool,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if allowed {
+                self.game_servers.insert(server, &());
+            } else {
+                self.game_servers.remove(server);
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn is_game_server(&self, server: AccountId) -> bool {
+            self.game_servers.contains(server)
+        }
+
+        /// Sets the levels of a collection. Their XP thresholds must strictly increase.
+        #[ink(message)]
+        pub fn set_levels(
+            &mut self,
+            collection_id: CollectionId,
+            levels: Vec<LevelUp>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            if levels.windows(2).any(|pair| pair[0].xp >= pair[1].xp) {
+                return Err(RmrkError::InvalidLevels);
+            }
+            self.level_ups.insert(collection_id, &levels);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn levels(&self, collection_id: CollectionId) -> Vec<LevelUp> {
+            self.level_ups.get(collection_id).unwrap_or_default()
+        }
+
+        /// Adds XP to an NFT and applies every level it reaches. Returns the NFT's level.
+        #[ink(message)]
+        pub fn grant_xp(
+            &mut self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+            amount: u64,
+        ) -> Result<u32, RmrkError> {
+            if !self.game_servers.contains(self.env().caller()) {
+                return Err(RmrkError::NotGameServer);
+            }
+            if self.env().extension().nfts(collection_id, nft_id).is_none() {
+                return Err(RmrkError::NftNotFound);
+            }
+            let xp = self
+                .stat(collection_id, nft_id, XP)
+                .saturating_add(amount)
+                .min(i64::MAX as u64);
+            self.env().extension().set_property(
+                collection_id,
+                Some(nft_id),
+                XP.to_vec(),
+                encode_stat(xp as i64),
+            )?;
+            self.env().emit_event(XpGranted {
+                collection_id,
+                nft_id,
+                amount,
+                xp,
+            });
+
+            let levels = self.level_ups.get(collection_id).unwrap_or_default();
+            let current = self.stat(collection_id, nft_id, LEVEL) as usize;
+            let reached = levels.iter().take_while(|level| level.xp <= xp).count();
+            if reached <= current {
+                return Ok(current as u32);
+            }
+            let owned = self.root_owner(collection_id, nft_id) == Some(self.env().account_id());
+            for (index, level) in levels[current..reached].iter().enumerate() {
+                let resource_id = self.add_resource(
+                    collection_id,
+                    nft_id,
+                    ResourceTypes::Basic(level.resource.clone()),
+                )?;
+                if owned {
+                    self.promote_resource(collection_id, nft_id, resource_id)?;
+                }
+                self.env().emit_event(LeveledUp {
+                    collection_id,
+                    nft_id,
+                    level: (current + index + 1) as u32,
+                    resource_id,
+                });
+            }
+            self.env().extension().set_property(
+                collection_id,
+                Some(nft_id),
+                LEVEL.to_vec(),
+                encode_stat(reached as i64),
+            )?;
+            Ok(reached as u32)
+        }
+    }
+
+    // Resource schedules
+    impl RmrkTestContract {
+        /// Sets the schedule of a collection, or removes it with `None`.
+        #[ink(message)]
+        pub fn set_resource_schedule(
+            &mut self,
+            collection_id: CollectionId,
+            schedule: Option<ResourceSchedule>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            match schedule {
+                Some(schedule) => {
+                    if schedule
+                        .entries
+                        .iter()
+                        .any(|entry| entry.end_block <= entry.start_block)
+                    {
+                        return Err(RmrkError::InvalidEndBlock);
+                    }
+                    self.resource_schedules.insert(collection_id, &schedule)
+                }
+                None => self.resource_schedules.remove(collection_id),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn resource_schedule(&self, collection_id: CollectionId) -> Option<ResourceSchedule> {
+            self.resource_schedules.get(collection_id)
+        }
+
+        /// Returns the resource the collection's schedule puts on top at the current block.
+        #[ink(message)]
+        pub fn active_resource(&self, collection_id: CollectionId) -> Option<ResourceId> {
+            self.resource_schedules
+                .get(collection_id)?
+                .active_at(self.env().block_number())
+        }
+
+        /// Puts the active resource on top of the priority list of each NFT. Anyone can call
+        /// this. Returns how many NFTs were reordered.
+        ///
+        /// NFTs that already show the active resource, lack it, or are not root-owned by the
+        /// contract are skipped, since the pallet only lets the root owner reorder priorities.
+        #[ink(message)]
+        pub fn apply_schedule(
+            &mut self,
+            collection_id: CollectionId,
+            nft_ids: Vec<NftId>,
+        ) -> Result<u32, RmrkError> {
+            let resource_id = match self.active_resource(collection_id) {
+                Some(resource_id) => resource_id,
+                None => return Ok(0),
+            };
+            let mut applied = 0;
+            for nft_id in nft_ids {
+                let skip = self.root_owner(collection_id, nft_id) != Some(self.env().account_id())
+                    || self
+                        .env()
+                        .extension()
+                        .resources(collection_id, nft_id, resource_id)
+                        .is_none()
+                    || self.resource_order(collection_id, nft_id).first() == Some(&resource_id);
+                if skip {
+                    continue;
+                }
+                self.promote_resource(collection_id, nft_id, resource_id)?;
+                applied += 1;
+                self.env().emit_event(ScheduleApplied {
+                    collection_id,
+                    nft_id,
+                    resource_id,
+                });
+            }
+            Ok(applied)
+        }
+    }
+
+    // Bundles
+    //
+    // Items are nested into a container minted while the contract owns it, so they never need
+    // accepting, and the container is then handed to the bundler like any other NFT. To unbundle,
+    // the holder deposits the container first so that the contract can move its children.
+    // Containers are identified by collection and id, so changing the bundle collection leaves
+    // existing bundles intact.
+    impl RmrkTestContract {
+        /// Sets the collection new bundle containers are minted into. The contract must be its
+        /// issuer.
+        #[ink(message)]
+        pub fn set_bundle_collection(
+            &mut self,
+            collection_id: CollectionId,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            let collection = self
+                .env()
+                .extension()
+                .collections(collection_id)
+                .ok_or(RmrkError::CollectionNotFound)?;
+            if collection.issuer != self.env().account_id() {
+                return Err(RmrkError::NotAuthorized);
+            }
+            self.bundle_collection = Some(collection_id);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn bundle_collection(&self) -> Option<CollectionId> {
+            self.bundle_collection
+        }
+
+        /// Nests NFTs the caller deposited into a new container and sends it to the caller.
+        /// Returns the container's collection and id.
+        #[ink(message)]
+        pub fn bundle(
+            &mut self,
+            items: Vec<(CollectionId, NftId)>,
+        ) -> Result<(CollectionId, NftId), RmrkError> {
+            let bundle_collection = self
+                .bundle_collection
+                .ok_or(RmrkError::BundlesNotConfigured)?;
+            if items.is_empty() {
+                return Err(RmrkError::EmptyBundle);
+            }
+            let owner = self.env().caller();
+            let container_id = self.env().extension().next_nft_id(bundle_collection);
+            self.env().extension().mint_nft(
+                self.env().account_id(),
+                bundle_collection,
+                None,
+                None,
+                Vec::new(),
+                true,
+                None,
+            )?;
+            let container = AccountIdOrCollectionNftTuple::CollectionAndNftTuple(
+                bundle_collection,
+                container_id,
+            );
+            for &(collection_id, nft_id) in &items {
+                self.lock_escrow(collection_id, nft_id)?;
+                self.escrows.remove((collection_id, nft_id));
+                self.env()
+                    .extension()
+                    .send(collection_id, nft_id, container.clone())?;
+                if self
+                    .env()
+                    .extension()
+                    .nfts(collection_id, nft_id)
+                    .is_some_and(|nft| nft.pending)
+                {
+                    self.env()
+                        .extension()
+                        .accept_nft(collection_id, nft_id, container.clone())?;
+                }
+            }
+            self.bundles
+                .insert((bundle_collection, container_id), &items);
+            self.env().extension().send(
+                bundle_collection,
+                container_id,
+                AccountIdOrCollectionNftTuple::AccountId(owner),
+            )?;
+            self.env().emit_event(Bundled {
+                collection_id: bundle_collection,
+                container_id,
+                owner,
+                items,
+            });
+            Ok((bundle_collection, container_id))
+        }
+
+        /// Sends the bundled items back to the caller and burns the container, which the caller
+        /// must have deposited.
+        ///
+        /// Items moved out of the container in the meantime are skipped. Anything nested into the
+        /// container outside of `bundle` is burned along with it.
+        #[ink(message)]
+        pub fn unbundle(
+            &mut self,
+            bundle_collection: CollectionId,
+            container_id: NftId,
+        ) -> Result<(), RmrkError> {
+            let items = self
+                .bundles
+                .get((bundle_collection, container_id))
+                .ok_or(RmrkError::NotBundle)?;
+            self.lock_escrow(bundle_collection, container_id)?;
+            let owner = self.env().caller();
+            for (collection_id, nft_id) in items {
+                if self
+                    .env()
+                    .extension()
+                    .children((bundle_collection, container_id), (collection_id, nft_id))
+                    .is_some()
+                {
+                    self.env().extension().send(
+                        collection_id,
+                        nft_id,
+                        AccountIdOrCollectionNftTuple::AccountId(owner),
+                    )?;
+                }
+            }
+            self.bundles.remove((bundle_collection, container_id));
+            self.escrows.remove((bundle_collection, container_id));
+            self.env()
+                .extension()
+                .burn_nft(bundle_collection, container_id, MAX_BURNS)?;
+            self.env().emit_event(Unbundled {
+                collection_id: bundle_collection,
+                container_id,
+                owner,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn bundle_items(
+            &self,
+            collection_id: CollectionId,
+            container_id: NftId,
+        ) -> Vec<(CollectionId, NftId)> {
+            self.bundles
+                .get((collection_id, container_id))
+                .unwrap_or_default()
+        }
+    }
+
+    // Collection migration
+    impl RmrkTestContract {
+        /// Sets the property keys copied when migrating NFTs out of `collection_id`. The chain
+        /// extension cannot list an NFT's properties, so only these keys are carried over.
+        #[ink(message)]
+        pub fn set_migration_keys(
+            &mut self,
+            collection_id: CollectionId,
+            keys: Vec<Vec<u8>>,
+        ) -> Result<(), RmrkError> {
+            self.ensure_admin()?;
+            self.migration_keys.insert(collection_id, &keys);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn migration_keys(&self, collection_id: CollectionId) -> Vec<Vec<u8>> {
+            self.migration_keys.get(collection_id).unwrap_or_default()
+        }
+
+        /// Re-mints NFTs of `from_collection` into `to_collection` and burns the originals.
+        /// Returns the new ids in the order of `nft_ids`.
+        ///
+        /// Royalty, metadata, transferability, resources, priorities and the migration keys'
+        /// properties are copied. The pallet only lets the contract burn NFTs it root-owns, so
+        /// each NFT must be held by the contract, deposited and idle, or nested under one of
+        /// those. A deposited NFT is replaced for its depositor and a nested one stays under its
+        /// parent. NFTs with children are refused, as are NFTs that are rented, bundled, badges or
+        /// tickets or that sit in any of those or in a deposited NFT.
+        #[ink(message)]
+        pub fn migrate(
+            &mut self,
+            from_collection: CollectionId,
+            to_collection: CollectionId,
+            nft_ids: Vec<NftId>,
+        ) -> Result<Vec<NftId>, RmrkError> {
+            self.ensure_admin()?;
+            let keys = self.migration_keys.get(from_collection).unwrap_or_default();
+            let mut migrated = Vec::new();
+            for nft_id in nft_ids {
+                let new_id = self.migrate_nft(from_collection, to_collection, nft_id, &keys)?;
+                migrated.push(new_id);
+            }
+            Ok(migrated)
+        }
+
+        #[ink(message)]
+        pub fn migrated_to(
+            &self,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) -> Option<(CollectionId, NftId)> {
+            self.migrations.get((collection_id, nft_id))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mock;
+        use ink_env::test;
+
+        fn init_test_contract() -> RmrkTestContract {
+            test::set_callee::<CustomEnvironment>(contract_account());
+            test::set_account_balance::<CustomEnvironment>(contract_account(), 0);
+            test::set_caller::<CustomEnvironment>(alice());
+            let test_contract = RmrkTestContract::new();
+
+            mock::register_chain_extension(contract_account());
+
+            test_contract
+        }
+
+        fn accounts() -> test::DefaultAccounts<CustomEnvironment> {
+            test::default_accounts()
+        }
+
+        fn alice() -> AccountId {
+            accounts().alice
+        }
+
+        fn bob() -> AccountId {
+            accounts().bob
+        }
+
+        fn contract_account() -> AccountId {
+            AccountId::from([0xff; 32])
+        }
+
+        fn charlie() -> AccountId {
+            accounts().charlie
+        }
+
+        fn balance(account: AccountId) -> Balance {
+            test::get_account_balance::<CustomEnvironment>(account).unwrap()
+        }
+
+        fn set_caller(caller: AccountId) {
+            test::set_caller::<CustomEnvironment>(caller);
+        }
+
+        /// Calls the next message as `caller`, transferring `value` to the contract.
+        fn pay_as(caller: AccountId, value: Balance) {
+            set_caller(caller);
+            test::transfer_in::<CustomEnvironment>(value);
+        }
+
+        /// Deposits an NFT owned by `owner` the way a user would: register, then send it.
+        fn deposit(
+            contract: &mut RmrkTestContract,
+            owner: AccountId,
+            collection_id: CollectionId,
+            nft_id: NftId,
+        ) {
+            set_caller(owner);
+            contract.deposit_nft(collection_id, nft_id).unwrap();
+            mock::transfer(collection_id, nft_id, contract_account());
+            contract.confirm_deposit(collection_id, nft_id).unwrap();
+        }
+
+        fn account(id: AccountId) -> AccountIdOrCollectionNftTuple {
+            AccountIdOrCollectionNftTuple::AccountId(id)
+        }
+
+        /// Creates a collection issued by the contract and mints `count` NFTs owned by `owner`.
+        fn collection_with_nfts(
+            contract: &mut RmrkTestContract,
+            owner: AccountId,
+            count: u32,
+        ) -> CollectionId {
+            let collection_id = contract.collection_index();
+            contract
+                .create_collection(Vec::new(), None, Vec::new())
+                .unwrap();
+            for _ in 0..count {
+                contract
+                    .mint_ntf(owner, collection_id, None, None, Vec::new(), true, None)
+                    .unwrap();
+            }
+            collection_id
+        }
+
+        #[ink::test]
+        fn send_batch_moves_every_item() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 2);
+
+            assert_eq!(
+                contract.send_batch(vec![
+                    (collection_id, 0, account(alice())),
+                    (collection_id, 1, account(bob())),
+                ]),
+                Ok(())
+            );
+            assert_eq!(mock::owner(collection_id, 0), Some(account(alice())));
+            assert_eq!(mock::owner(collection_id, 1), Some(account(bob())));
+        }
+
+        #[ink::test]
+        fn send_batch_stops_at_first_failure() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 2);
+
+            assert_eq!(
+                contract.send_batch(vec![
+                    (collection_id, 0, account(bob())),
+                    (collection_id, 2, account(alice())),
+                    (collection_id, 1, account(bob())),
+                ]),
+                Err(RmrkError::NftNotFound)
+            );
+            assert_eq!(
+                mock::owner(collection_id, 0),
+                Some(account(contract_account()))
+            );
+            assert_eq!(
+                mock::owner(collection_id, 1),
+                Some(account(contract_account()))
+            );
+        }
+
+        #[ink::test]
+        fn send_batch_returns_pallet_error_of_middle_item() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 3);
+            set_caller(bob());
+            assert_eq!(
+                contract.send_batch(vec![(collection_id, 0, account(bob()))]),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+
+            // The pallet refuses to nest NFT 1 into an NFT that does not exist
+            assert_eq!(
+                contract.send_batch(vec![
+                    (collection_id, 0, account(bob())),
+                    (
+                        collection_id,
+                        1,
+                        AccountIdOrCollectionNftTuple::CollectionAndNftTuple(collection_id, 9)
+                    ),
+                    (collection_id, 2, account(bob())),
+                ]),
+                Err(RmrkError::ErrorCode(RmrkErrorCode::Failed))
+            );
+            // The off-chain environment keeps the first send; on-chain the error reverts it
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+            assert_eq!(
+                mock::owner(collection_id, 1),
+                Some(account(contract_account()))
+            );
+            assert_eq!(
+                mock::owner(collection_id, 2),
+                Some(account(contract_account()))
+            );
+        }
+
+        #[ink::test]
+        fn accept_batch_accepts_pending_children() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 3);
+            contract.send(collection_id, 0, account(alice())).unwrap();
+            let parent = AccountIdOrCollectionNftTuple::CollectionAndNftTuple(collection_id, 0);
+            contract
+                .send_batch(vec![
+                    (collection_id, 1, parent.clone()),
+                    (collection_id, 2, parent.clone()),
+                ])
+                .unwrap();
+            assert!(contract.nfts(collection_id, 1).unwrap().pending);
+
+            mock::transfer(collection_id, 0, contract_account());
+            assert_eq!(
+                contract.accept_batch(vec![
+                    (collection_id, 1, parent.clone()),
+                    (collection_id, 2, parent),
+                ]),
+                Ok(())
+            );
+            assert!(!contract.nfts(collection_id, 1).unwrap().pending);
+            assert!(!contract.nfts(collection_id, 2).unwrap().pending);
+        }
+
+        fn leaf(collection_id: CollectionId, children: Vec<MintTreeSpec>) -> MintTreeSpec {
+            MintTreeSpec {
+                collection_id,
+                royalty_recipient: None,
+                royalty: None,
+                metadata: Vec::new(),
+                transferable: true,
+                resources: None,
+                children,
+            }
+        }
+
+        #[ink::test]
+        fn mint_tree_nests_children_into_fresh_parents() {
+            let mut contract = init_test_contract();
+            let characters = collection_with_nfts(&mut contract, alice(), 1);
+            let items = collection_with_nfts(&mut contract, alice(), 0);
+
+            let tree = contract
+                .mint_tree(
+                    alice(),
+                    leaf(
+                        characters,
+                        vec![
+                            leaf(items, vec![leaf(items, Vec::new())]),
+                            leaf(items, Vec::new()),
+                        ],
+                    ),
+                )
+                .unwrap();
+
+            assert_eq!(
+                tree,
+                NftIdTree {
+                    collection_id: characters,
+                    nft_id: 1,
+                    children: vec![
+                        NftIdTree {
+                            collection_id: items,
+                            nft_id: 0,
+                            children: vec![NftIdTree {
+                                collection_id: items,
+                                nft_id: 1,
+                                children: Vec::new(),
+                            }],
+                        },
+                        NftIdTree {
+                            collection_id: items,
+                            nft_id: 2,
+                            children: Vec::new(),
+                        },
+                    ],
+                }
+            );
+            assert_eq!(contract.children((characters, 1), (items, 0)), Some(()));
+            assert_eq!(contract.children((items, 0), (items, 1)), Some(()));
+            assert_eq!(contract.children((characters, 1), (items, 2)), Some(()));
+        }
+
+        #[ink::test]
+        fn deposited_nfts_cannot_be_moved_through_send() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+
+            set_caller(bob());
+            assert_eq!(
+                contract.send(collection_id, 0, account(bob())),
+                Err(RmrkError::NftInEscrow)
+            );
+            assert_eq!(
+                contract.burn_nft(collection_id, 0, 1),
+                Err(RmrkError::NftInEscrow)
+            );
+            assert_eq!(
+                contract.withdraw_nft(collection_id, 0),
+                Err(RmrkError::NotNftOwner)
+            );
+
+            set_caller(alice());
+            assert_eq!(contract.withdraw_nft(collection_id, 0), Ok(()));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(alice())));
+        }
+
+        #[ink::test]
+        fn stale_deposits_do_not_claim_nfts_sent_later() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, bob(), 1);
+            set_caller(bob());
+            contract.deposit_nft(collection_id, 0).unwrap();
+            mock::transfer(collection_id, 0, charlie());
+            advance_blocks(1);
+            mock::transfer(collection_id, 0, contract_account());
+
+            assert_eq!(
+                contract.confirm_deposit(collection_id, 0),
+                Err(RmrkError::NotDeposited)
+            );
+            assert_eq!(
+                contract.withdraw_nft(collection_id, 0),
+                Err(RmrkError::NotDeposited)
+            );
+            assert_eq!(
+                contract.send(collection_id, 0, account(bob())),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(charlie());
+            assert_eq!(
+                contract.burn_nft(collection_id, 0, 1),
+                Err(RmrkError::NotAuthorized)
+            );
+
+            // Only the admin can hand back an NFT that arrived without a confirmed deposit
+            set_caller(alice());
+            contract.send(collection_id, 0, account(charlie())).unwrap();
+            assert_eq!(mock::owner(collection_id, 0), Some(account(charlie())));
+        }
+
+        #[ink::test]
+        fn buy_pays_royalty_and_seller() {
+            let mut contract = init_test_contract();
+            let collection_id = contract.collection_index();
+            contract
+                .create_collection(Vec::new(), None, Vec::new())
+                .unwrap();
+            contract
+                .mint_ntf(
+                    alice(),
+                    collection_id,
+                    Some(charlie()),
+                    Some(100_000),
+                    Vec::new(),
+                    true,
+                    None,
+                )
+                .unwrap();
+            deposit(&mut contract, alice(), collection_id, 0);
+            assert_eq!(contract.list(collection_id, 0, 500), Ok(()));
+            let (alice_before, charlie_before) = (balance(alice()), balance(charlie()));
+
+            pay_as(bob(), 400);
+            assert_eq!(contract.buy(collection_id, 0), Err(RmrkError::WrongPayment));
+            pay_as(bob(), 500);
+            assert_eq!(contract.buy(collection_id, 0), Ok(()));
+
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+            assert_eq!(balance(charlie()), charlie_before + 50);
+            assert_eq!(balance(alice()), alice_before + 450);
+            assert_eq!(contract.listing(collection_id, 0), None);
+            assert_eq!(contract.escrow(collection_id, 0), None);
+        }
+
+        #[ink::test]
+        fn only_seller_updates_or_delists() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+            contract.list(collection_id, 0, 500).unwrap();
+            assert_eq!(
+                contract.withdraw_nft(collection_id, 0),
+                Err(RmrkError::NftLocked)
+            );
+
+            set_caller(bob());
+            assert_eq!(
+                contract.update_price(collection_id, 0, 1),
+                Err(RmrkError::NotNftOwner)
+            );
+            assert_eq!(
+                contract.delist(collection_id, 0),
+                Err(RmrkError::NotNftOwner)
+            );
+
+            set_caller(alice());
+            assert_eq!(contract.update_price(collection_id, 0, 700), Ok(()));
+            assert_eq!(contract.listing(collection_id, 0).unwrap().price, 700);
+            assert_eq!(contract.delist(collection_id, 0), Ok(()));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(alice())));
+        }
+
+        #[ink::test]
+        fn royalty_info_applies_permill_without_overflow() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            for royalty in [25_000, 2_000_000] {
+                contract
+                    .mint_ntf(
+                        alice(),
+                        collection_id,
+                        Some(charlie()),
+                        Some(royalty),
+                        Vec::new(),
+                        true,
+                        None,
+                    )
+                    .unwrap();
+            }
+
+            assert_eq!(contract.royalty_info(collection_id, 0, 1_000), None);
+            assert_eq!(contract.royalty_info(collection_id, 9, 1_000), None);
+            assert_eq!(
+                contract.royalty_info(collection_id, 1, 1_999),
+                Some((charlie(), 49))
+            );
+            assert_eq!(
+                contract.royalty_info(collection_id, 1, Balance::MAX),
+                Some((charlie(), Balance::MAX / 40))
+            );
+            assert_eq!(
+                contract.royalty_info(collection_id, 2, Balance::MAX),
+                Some((charlie(), Balance::MAX))
+            );
+        }
+
+        fn advance_blocks(count: u32) {
+            for _ in 0..count {
+                test::advance_block::<CustomEnvironment>();
+            }
+        }
+
+        #[ink::test]
+        fn auction_refunds_outbid_bidder_and_pays_winner_bid() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+            assert_eq!(
+                contract.create_auction(collection_id, 0, 100, 10, 5, 0),
+                Ok(())
+            );
+
+            pay_as(bob(), 99);
+            assert_eq!(contract.bid(collection_id, 0), Err(RmrkError::BidTooLow));
+            pay_as(bob(), 100);
+            assert_eq!(contract.bid(collection_id, 0), Ok(()));
+            let bob_after_bid = balance(bob());
+            pay_as(charlie(), 109);
+            assert_eq!(contract.bid(collection_id, 0), Err(RmrkError::BidTooLow));
+            pay_as(charlie(), 110);
+            assert_eq!(contract.bid(collection_id, 0), Ok(()));
+            assert_eq!(balance(bob()), bob_after_bid);
+            assert_eq!(contract.pending_return(bob()), 100);
+            set_caller(bob());
+            assert_eq!(contract.withdraw(), Ok(100));
+            assert_eq!(balance(bob()), bob_after_bid + 100);
+            assert_eq!(contract.withdraw(), Ok(0));
+            assert_eq!(balance(bob()), bob_after_bid + 100);
+
+            assert_eq!(
+                contract.settle_auction(collection_id, 0),
+                Err(RmrkError::AuctionNotEnded)
+            );
+            let alice_before = balance(alice());
+            advance_blocks(5);
+            pay_as(bob(), 200);
+            assert_eq!(contract.bid(collection_id, 0), Err(RmrkError::AuctionEnded));
+            assert_eq!(contract.settle_auction(collection_id, 0), Ok(()));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(charlie())));
+            assert_eq!(balance(alice()), alice_before + 110);
+        }
+
+        #[ink::test]
+        fn late_bid_extends_auction() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+            contract
+                .create_auction(collection_id, 0, 0, 1, 10, 3)
+                .unwrap();
+
+            advance_blocks(8);
+            pay_as(bob(), 1);
+            contract.bid(collection_id, 0).unwrap();
+            assert_eq!(contract.auction(collection_id, 0).unwrap().end_block, 11);
+        }
+
+        #[ink::test]
+        fn auction_without_bids_returns_nft() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 2);
+            deposit(&mut contract, alice(), collection_id, 0);
+            deposit(&mut contract, alice(), collection_id, 1);
+            contract
+                .create_auction(collection_id, 0, 0, 1, 2, 0)
+                .unwrap();
+            contract
+                .create_auction(collection_id, 1, 0, 1, 2, 0)
+                .unwrap();
+
+            set_caller(bob());
+            assert_eq!(
+                contract.cancel_auction(collection_id, 0),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(alice());
+            assert_eq!(contract.cancel_auction(collection_id, 0), Ok(()));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(alice())));
+
+            advance_blocks(2);
+            assert_eq!(contract.settle_auction(collection_id, 1), Ok(()));
+            assert_eq!(mock::owner(collection_id, 1), Some(account(alice())));
+        }
+
+        #[ink::test]
+        fn dutch_price_decays_linearly_or_stepwise() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 2);
+            deposit(&mut contract, alice(), collection_id, 0);
+            deposit(&mut contract, alice(), collection_id, 1);
+            contract
+                .create_dutch_auction(collection_id, 0, 1_000, 100, 0, 9, PriceDecay::Linear)
+                .unwrap();
+            contract
+                .create_dutch_auction(
+                    collection_id,
+                    1,
+                    1_000,
+                    100,
+                    0,
+                    9,
+                    PriceDecay::Stepwise { step_blocks: 3 },
+                )
+                .unwrap();
+
+            advance_blocks(4);
+            assert_eq!(contract.current_price(collection_id, 0), Some(600));
+            assert_eq!(contract.current_price(collection_id, 1), Some(700));
+            advance_blocks(10);
+            assert_eq!(contract.current_price(collection_id, 0), Some(100));
+            assert_eq!(contract.current_price(collection_id, 1), Some(100));
+        }
+
+        #[ink::test]
+        fn buy_dutch_charges_current_price_and_refunds_rest() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+            contract
+                .create_dutch_auction(collection_id, 0, 90, 0, 0, 9, PriceDecay::Linear)
+                .unwrap();
+            advance_blocks(3);
+
+            pay_as(bob(), 59);
+            assert_eq!(
+                contract.buy_dutch(collection_id, 0),
+                Err(RmrkError::BidTooLow)
+            );
+            let alice_before = balance(alice());
+            pay_as(bob(), 70);
+            let bob_after_payment = balance(bob());
+            assert_eq!(contract.buy_dutch(collection_id, 0), Ok(()));
+
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+            assert_eq!(balance(alice()), alice_before + 60);
+            assert_eq!(balance(bob()), bob_after_payment + 10);
+            assert_eq!(contract.current_price(collection_id, 0), None);
+        }
+
+        #[ink::test]
+        fn collection_offer_accepts_any_nft_of_collection() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 2);
+            let other_collection = collection_with_nfts(&mut contract, alice(), 1);
+            pay_as(bob(), 300);
+            let offer_id = contract
+                .make_offer(OfferTarget::Collection(collection_id), 10)
+                .unwrap();
+
+            deposit(&mut contract, alice(), other_collection, 0);
+            assert_eq!(
+                contract.accept_offer(offer_id, other_collection, 0),
+                Err(RmrkError::OfferMismatch)
+            );
+            assert_eq!(
+                contract.accept_offer(offer_id, collection_id, 1),
+                Err(RmrkError::NotDeposited)
+            );
+            deposit(&mut contract, alice(), collection_id, 1);
+            let alice_before = balance(alice());
+            assert_eq!(contract.accept_offer(offer_id, collection_id, 1), Ok(()));
+
+            assert_eq!(mock::owner(collection_id, 1), Some(account(bob())));
+            assert_eq!(balance(alice()), alice_before + 300);
+            assert_eq!(contract.offer(offer_id), None);
+        }
+
+        #[ink::test]
+        fn accepting_offer_on_listed_nft_cancels_listing() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), collection_id, 0);
+            contract.list(collection_id, 0, 500).unwrap();
+            pay_as(bob(), 300);
+            let offer_id = contract
+                .make_offer(OfferTarget::Nft(collection_id, 0), 10)
+                .unwrap();
+
+            set_caller(charlie());
+            assert_eq!(
+                contract.accept_offer(offer_id, collection_id, 0),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(alice());
+            assert_eq!(contract.accept_offer(offer_id, collection_id, 0), Ok(()));
+
+            assert_eq!(contract.listing(collection_id, 0), None);
+            assert_eq!(contract.escrow(collection_id, 0), None);
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+        }
+
+        #[ink::test]
+        fn expired_offer_is_refunded_by_anyone() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            pay_as(bob(), 300);
+            let offer_id = contract
+                .make_offer(OfferTarget::Nft(collection_id, 0), 2)
+                .unwrap();
+            let bob_after_offer = balance(bob());
+
+            set_caller(charlie());
+            assert_eq!(
+                contract.cancel_offer(offer_id),
+                Err(RmrkError::NotAuthorized)
+            );
+            advance_blocks(2);
+            deposit(&mut contract, alice(), collection_id, 0);
+            assert_eq!(
+                contract.accept_offer(offer_id, collection_id, 0),
+                Err(RmrkError::OfferExpired)
+            );
+            set_caller(charlie());
+            assert_eq!(contract.cancel_offer(offer_id), Ok(()));
+            assert_eq!(balance(bob()), bob_after_offer + 300);
+        }
+
+        #[ink::test]
+        fn swap_exchanges_nfts_and_balances() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            contract
+                .mint_ntf(bob(), collection_id, None, None, Vec::new(), true, None)
+                .unwrap();
+            deposit(&mut contract, alice(), collection_id, 0);
+            pay_as(alice(), 50);
+            let swap_id = contract
+                .propose_swap(
+                    bob(),
+                    vec![(collection_id, 0)],
+                    vec![(collection_id, 1)],
+                    20,
+                )
+                .unwrap();
+
+            set_caller(charlie());
+            assert_eq!(
+                contract.execute_swap(swap_id),
+                Err(RmrkError::NotAuthorized)
+            );
+            pay_as(bob(), 20);
+            assert_eq!(contract.execute_swap(swap_id), Err(RmrkError::NotDeposited));
+            deposit(&mut contract, bob(), collection_id, 1);
+            let (alice_before, bob_before) = (balance(alice()), balance(bob()));
+            pay_as(bob(), 20);
+            assert_eq!(contract.execute_swap(swap_id), Ok(()));
+
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+            assert_eq!(mock::owner(collection_id, 1), Some(account(alice())));
+            assert_eq!(balance(alice()), alice_before + 20);
+            assert_eq!(balance(bob()), bob_before - 20 + 50);
+        }
+
+        #[ink::test]
+        fn cancelled_swap_returns_locked_deposits() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            contract
+                .mint_ntf(bob(), collection_id, None, None, Vec::new(), true, None)
+                .unwrap();
+            deposit(&mut contract, alice(), collection_id, 0);
+            deposit(&mut contract, bob(), collection_id, 1);
+            set_caller(alice());
+            let swap_id = contract
+                .propose_swap(bob(), vec![(collection_id, 0)], vec![(collection_id, 1)], 0)
+                .unwrap();
+
+            set_caller(bob());
+            assert_eq!(contract.cancel_swap(swap_id), Ok(()));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(alice())));
+            assert_eq!(contract.swap(swap_id), None);
+            // Bob's deposit was never locked by the swap and stays with the contract
+            assert_eq!(
+                mock::owner(collection_id, 1),
+                Some(account(contract_account()))
+            );
+            assert_eq!(contract.withdraw_nft(collection_id, 1), Ok(()));
+            assert_eq!(mock::owner(collection_id, 1), Some(account(bob())));
+        }
+
+        fn mint_config(price: Balance, max_per_account: Option<u32>) -> MintConfig {
+            MintConfig {
+                price,
+                max_per_account,
+                public_start_block: Some(0),
+                metadata: b"ipfs://drop".to_vec(),
+                royalty_recipient: None,
+                royalty: None,
+                transferable: true,
+            }
+        }
+
+        #[ink::test]
+        fn public_mint_charges_price_and_enforces_caps() {
+            let mut contract = init_test_contract();
+            let collection_id = contract.collection_index();
+            contract
+                .create_collection(Vec::new(), Some(3), Vec::new())
+                .unwrap();
+            set_caller(bob());
+            assert_eq!(
+                contract.set_mint_config(collection_id, Some(mint_config(10, Some(2)))),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+            contract
+                .set_mint_config(collection_id, Some(mint_config(10, Some(2))))
+                .unwrap();
+
+            pay_as(bob(), 15);
+            assert_eq!(
+                contract.public_mint(collection_id, 2),
+                Err(RmrkError::WrongPayment)
+            );
+            pay_as(bob(), 20);
+            assert_eq!(contract.public_mint(collection_id, 2), Ok(()));
+            assert_eq!(mock::owner(collection_id, 1), Some(account(bob())));
+            assert_eq!(
+                contract.nfts(collection_id, 1).unwrap().metadata,
+                b"ipfs://drop"
+            );
+            pay_as(bob(), 10);
+            assert_eq!(
+                contract.public_mint(collection_id, 1),
+                Err(RmrkError::MintLimitExceeded)
+            );
+            pay_as(charlie(), 20);
+            assert_eq!(
+                contract.public_mint(collection_id, 2),
+                Err(RmrkError::SupplyExceeded)
+            );
+            assert_eq!(contract.treasury(), 20);
+
+            set_caller(charlie());
+            assert_eq!(
+                contract.mint_ntf(charlie(), collection_id, None, None, vec![], true, None),
+                Err(RmrkError::NotAuthorized)
+            );
+            assert_eq!(
+                contract.change_collection_issuer(collection_id, charlie()),
+                Err(RmrkError::NotAuthorized)
+            );
+        }
+
+        #[ink::test]
+        fn only_admin_withdraws_treasury() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 0);
+            contract
+                .set_mint_config(collection_id, Some(mint_config(10, None)))
+                .unwrap();
+            pay_as(bob(), 30);
+            contract.public_mint(collection_id, 3).unwrap();
+
+            set_caller(bob());
+            assert_eq!(
+                contract.withdraw_treasury(bob(), 30),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+            assert_eq!(
+                contract.withdraw_treasury(charlie(), 31),
+                Err(RmrkError::InsufficientTreasury)
+            );
+            let charlie_before = balance(charlie());
+            assert_eq!(contract.withdraw_treasury(charlie(), 30), Ok(()));
+            assert_eq!(balance(charlie()), charlie_before + 30);
+            assert_eq!(contract.treasury(), 0);
+        }
+
+        #[ink::test]
+        fn allowlist_mint_checks_proof_phase_and_allowance() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 0);
+            let config = MintConfig {
+                public_start_block: None,
+                ..mint_config(100, Some(1))
+            };
+            contract
+                .set_mint_config(collection_id, Some(config))
+                .unwrap();
+            let bob_leaf = merkle::leaf(&bob(), 2);
+            let charlie_leaf = merkle::leaf(&charlie(), 1);
+            let phase = MintPhase {
+                merkle_root: merkle::hash_pair(&bob_leaf, &charlie_leaf),
+                start_block: 1,
+                end_block: 3,
+                price: 5,
+            };
+            contract
+                .set_mint_phase(collection_id, 0, Some(phase))
+                .unwrap();
+
+            pay_as(bob(), 10);
+            assert_eq!(
+                contract.allowlist_mint(collection_id, 0, 2, 2, vec![charlie_leaf]),
+                Err(RmrkError::PhaseNotActive)
+            );
+            advance_blocks(1);
+            assert_eq!(
+                contract.allowlist_mint(collection_id, 0, 2, 3, vec![charlie_leaf]),
+                Err(RmrkError::InvalidProof)
+            );
+            assert_eq!(
+                contract.allowlist_mint(collection_id, 0, 2, 2, vec![charlie_leaf]),
+                Ok(())
+            );
+            assert_eq!(contract.phase_claimed(collection_id, 0, bob()), 2);
+            assert_eq!(mock::owner(collection_id, 1), Some(account(bob())));
+            pay_as(charlie(), 100);
+            assert_eq!(
+                contract.public_mint(collection_id, 1),
+                Err(RmrkError::SaleNotStarted)
+            );
+            pay_as(bob(), 5);
+            assert_eq!(
+                contract.allowlist_mint(collection_id, 0, 1, 2, vec![charlie_leaf]),
+                Err(RmrkError::MintLimitExceeded)
+            );
+
+            advance_blocks(2);
+            pay_as(charlie(), 5);
+            assert_eq!(
+                contract.allowlist_mint(collection_id, 0, 1, 1, vec![bob_leaf]),
+                Err(RmrkError::PhaseNotActive)
+            );
+            assert_eq!(contract.treasury(), 10);
+        }
+
+        /// Signs `voucher` for the test contract the way the minting backend would.
+        fn sign_voucher(key: &secp256k1::SecretKey, voucher: &MintVoucher) -> (Vec<u8>, [u8; 65]) {
+            let encoded = voucher.encode();
+            let mut payload = AsRef::<[u8]>::as_ref(&contract_account()).to_vec();
+            payload.extend_from_slice(&encoded);
+            assert_eq!(payload.len(), 32 + encoded.len());
+            let mut message_hash = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&payload, &mut message_hash);
+            let message = secp256k1::Message::from_slice(&message_hash).unwrap();
+            let (recovery_id, compact) = secp256k1::Secp256k1::new()
+                .sign_ecdsa_recoverable(&message, key)
+                .serialize_compact();
+            let mut signature = [0u8; 65];
+            signature[..64].copy_from_slice(&compact);
+            signature[64] = recovery_id.to_i32() as u8;
+            (encoded, signature)
+        }
+
+        #[ink::test]
+        fn redeem_voucher_mints_once_per_nonce() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 0);
+            let key = secp256k1::SecretKey::from_slice(&[7; 32]).unwrap();
+            let other_key = secp256k1::SecretKey::from_slice(&[8; 32]).unwrap();
+            let public_key =
+                secp256k1::PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), &key);
+            let mut signer = [0u8; 20];
+            ink_env::ecdsa_to_eth_address(&public_key.serialize(), &mut signer).unwrap();
+            contract.set_voucher_signer(Some(signer)).unwrap();
+            let voucher = MintVoucher {
+                collection_id,
+                metadata: b"ipfs://voucher".to_vec(),
+                resources: None,
+                recipient: charlie(),
+                nonce: 1,
+                expiry: 5,
+            };
+
+            set_caller(bob());
+            let (encoded, forged) = sign_voucher(&other_key, &voucher);
+            assert_eq!(
+                contract.redeem_voucher(encoded, forged),
+                Err(RmrkError::InvalidSignature)
+            );
+            let (encoded, signature) = sign_voucher(&key, &voucher);
+            assert_eq!(contract.redeem_voucher(encoded.clone(), signature), Ok(0));
+            assert_eq!(mock::owner(collection_id, 0), Some(account(charlie())));
+            assert_eq!(
+                contract.redeem_voucher(encoded, signature),
+                Err(RmrkError::NonceUsed)
+            );
+
+            advance_blocks(5);
+            let (encoded, signature) = sign_voucher(
+                &key,
+                &MintVoucher {
+                    nonce: 2,
+                    ..voucher
+                },
+            );
+            assert_eq!(
+                contract.redeem_voucher(encoded, signature),
+                Err(RmrkError::VoucherExpired)
+            );
+        }
+
+        fn basic(src: &[u8]) -> BasicResource {
+            BasicResource {
+                src: Some(src.to_vec()),
+                metadata: None,
+                license: None,
+                thumb: None,
+            }
+        }
+
+        fn basic_resource(src: &[u8]) -> ResourceTypes {
+            ResourceTypes::Basic(basic(src))
+        }
+
+        #[ink::test]
+        fn reveal_requires_seed_matching_provenance() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 0);
+            for _ in 0..2 {
+                contract
+                    .mint_ntf(
+                        contract_account(),
+                        collection_id,
+                        None,
+                        None,
+                        b"placeholder".to_vec(),
+                        true,
+                        Some(vec![basic_resource(b"ipfs://placeholder")]),
+                    )
+                    .unwrap();
+            }
+            let seed = b"salt".to_vec();
+            let resources = [
+                basic_resource(b"ipfs://real/0"),
+                basic_resource(b"ipfs://real/1"),
+            ];
+            let resource_hashes: Vec<[u8; 32]> = resources
+                .iter()
+                .map(|resource| {
+                    let mut hash = [0u8; 32];
+                    ink_env::hash_encoded::<Blake2x256, _>(resource, &mut hash);
+                    hash
+                })
+                .collect();
+            let mut provenance_hash = [0u8; 32];
+            ink_env::hash_encoded::<Blake2x256, _>(
+                &(&seed, &resource_hashes),
+                &mut provenance_hash,
+            );
+            contract
+                .commit_provenance(collection_id, provenance_hash)
+                .unwrap();
+
+            assert_eq!(
+                contract.reveal_batch(collection_id, vec![(0, resources[0].clone())]),
+                Err(RmrkError::NotRevealed)
+            );
+            assert_eq!(
+                contract.publish_reveal_seed(
+                    collection_id,
+                    b"guess".to_vec(),
+                    resource_hashes.clone()
+                ),
+                Err(RmrkError::InvalidSeed)
+            );
+            let mut reordered = resource_hashes.clone();
+            reordered.reverse();
+            assert_eq!(
+                contract.publish_reveal_seed(collection_id, seed.clone(), reordered),
+                Err(RmrkError::InvalidSeed)
+            );
+            assert_eq!(
+                contract.publish_reveal_seed(collection_id, seed, resource_hashes),
+                Ok(())
+            );
+
+            let offset = contract.reveal(collection_id).unwrap().offset as usize;
+            let assigned = |nft_id: NftId, shift: usize| {
+                (
+                    nft_id,
+                    resources[(nft_id as usize + offset + shift) % 2].clone(),
+                )
+            };
+            assert_eq!(
+                contract.reveal_batch(collection_id, vec![assigned(0, 1), assigned(1, 1)]),
+                Err(RmrkError::ResourceMismatch)
+            );
+            assert_eq!(
+                contract.reveal_batch(collection_id, vec![assigned(0, 0), assigned(1, 0)]),
+                Ok(())
+            );
+            assert!(contract.revealed(collection_id, 1));
+            assert_eq!(
+                contract.reveal_batch(collection_id, vec![assigned(1, 0)]),
+                Err(RmrkError::AlreadyRevealed)
+            );
+            assert_eq!(contract.next_resource_id(collection_id, 1), 2);
+
+            for nft_id in 0..2 {
+                assert_eq!(contract.priorities(collection_id, nft_id, 1), Some(0));
+                assert_eq!(contract.priorities(collection_id, nft_id, 0), Some(1));
+            }
+        }
+
+        fn reward(collection_id: CollectionId, metadata: &[u8]) -> RewardSpec {
+            RewardSpec {
+                collection_id,
+                metadata: metadata.to_vec(),
+                transferable: true,
+                resources: None,
+            }
+        }
+
+        #[ink::test]
+        fn opened_boxes_are_burned_and_rewarded_after_reveal() {
+            let mut contract = init_test_contract();
+            let boxes = collection_with_nfts(&mut contract, alice(), 2);
+            let characters = collection_with_nfts(&mut contract, alice(), 1);
+            let items = collection_with_nfts(&mut contract, alice(), 0);
+            contract
+                .set_loot_table(
+                    boxes,
+                    LootTable {
+                        entries: vec![(1, reward(items, b"common")), (0, reward(items, b"never"))],
+                    },
+                )
+                .unwrap();
+            let seed = b"round zero".to_vec();
+            let mut commitment = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&seed, &mut commitment);
+            assert_eq!(contract.commit_loot_seed(boxes, commitment), Ok(0));
+
+            deposit(&mut contract, alice(), boxes, 0);
+            deposit(&mut contract, alice(), boxes, 1);
+            set_caller(bob());
+            assert_eq!(
+                contract.open_box(boxes, 0, None),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(alice());
+            assert_eq!(contract.open_box(boxes, 0, None), Ok(()));
+            assert_eq!(contract.open_box(boxes, 1, Some((characters, 0))), Ok(()));
+            assert_eq!(contract.withdraw_nft(boxes, 0), Err(RmrkError::NftLocked));
+            assert_eq!(contract.claim_loot(boxes, 0), Err(RmrkError::NotRevealed));
+            assert_eq!(contract.refund_box(boxes, 0), Err(RmrkError::RefundNotDue));
+
+            assert_eq!(
+                contract.commit_loot_seed(boxes, commitment),
+                Err(RmrkError::AlreadyCommitted)
+            );
+            contract.reveal_loot_seed(boxes, seed).unwrap();
+            set_caller(charlie());
+            assert_eq!(contract.claim_loot(boxes, 0), Ok((items, 0)));
+            assert_eq!(contract.claim_loot(boxes, 1), Ok((items, 1)));
+            assert_eq!(contract.nfts(boxes, 0), None);
+            assert_eq!(mock::owner(items, 0), Some(account(alice())));
+            assert_eq!(contract.nfts(items, 0).unwrap().metadata, b"common");
+            assert_eq!(contract.children((characters, 0), (items, 1)), Some(()));
+            assert_eq!(contract.claim_loot(boxes, 0), Err(RmrkError::BoxNotOpened));
+        }
+
+        #[ink::test]
+        fn unrevealed_boxes_are_refunded_after_timeout() {
+            let mut contract = init_test_contract();
+            let boxes = collection_with_nfts(&mut contract, alice(), 1);
+            let items = collection_with_nfts(&mut contract, alice(), 0);
+            contract
+                .set_loot_table(
+                    boxes,
+                    LootTable {
+                        entries: vec![(1, reward(items, b"common"))],
+                    },
+                )
+                .unwrap();
+            let seed = b"withheld".to_vec();
+            let mut commitment = [0u8; 32];
+            ink_env::hash_bytes::<Blake2x256>(&seed, &mut commitment);
+            contract.commit_loot_seed(boxes, commitment).unwrap();
+            deposit(&mut contract, alice(), boxes, 0);
+            contract.open_box(boxes, 0, None).unwrap();
+
+            set_caller(bob());
+            advance_blocks(LOOT_REVEAL_TIMEOUT - 1);
+            assert_eq!(contract.refund_box(boxes, 0), Err(RmrkError::RefundNotDue));
+            advance_blocks(1);
+            assert_eq!(contract.refund_box(boxes, 0), Ok(()));
+            assert_eq!(mock::owner(boxes, 0), Some(account(alice())));
+            assert_eq!(contract.opened_box(boxes, 0), None);
+
+            set_caller(alice());
+            contract.reveal_loot_seed(boxes, seed).unwrap();
+            assert_eq!(contract.claim_loot(boxes, 0), Err(RmrkError::BoxNotOpened));
+        }
+
+        #[test]
+        fn loot_table_picks_by_weight() {
+            let table = LootTable {
+                entries: vec![
+                    (1, reward(0, b"a")),
+                    (0, reward(0, b"b")),
+                    (2, reward(0, b"c")),
+                ],
+            };
+            let picks: Vec<_> = (0..6)
+                .map(|roll| table.pick(roll).unwrap().metadata.clone())
+                .collect();
+            assert_eq!(picks, [b"a", b"c", b"c", b"a", b"c", b"c"]);
+            assert_eq!(
+                LootTable {
+                    entries: Vec::new()
+                }
+                .pick(7),
+                None
+            );
+        }
+
+        #[ink::test]
+        fn craft_burns_inputs_and_mints_output() {
+            let mut contract = init_test_contract();
+            let ore = collection_with_nfts(&mut contract, alice(), 3);
+            let wood = collection_with_nfts(&mut contract, alice(), 1);
+            let swords = collection_with_nfts(&mut contract, alice(), 0);
+            let recipe = Recipe {
+                inputs: vec![(ore, 2), (wood, 1)],
+                output: CraftOutput {
+                    collection_id: swords,
+                    metadata: b"sword".to_vec(),
+                    transferable: true,
+                    resources: None,
+                    properties: vec![(b"attack".to_vec(), b"7".to_vec())],
+                },
+                enabled: false,
+            };
+            set_caller(bob());
+            assert_eq!(
+                contract.add_recipe(recipe.clone()),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+            let recipe_id = contract.add_recipe(recipe).unwrap();
+            for (collection_id, nft_id) in [(ore, 0), (ore, 1), (ore, 2), (wood, 0)] {
+                deposit(&mut contract, alice(), collection_id, nft_id);
+            }
+
+            let inputs = vec![(ore, 0), (ore, 1), (wood, 0)];
+            assert_eq!(
+                contract.craft(recipe_id, inputs.clone()),
+                Err(RmrkError::RecipeDisabled)
+            );
+            set_caller(alice());
+            contract.set_recipe_enabled(recipe_id, true).unwrap();
+            assert_eq!(
+                contract.craft(recipe_id, vec![(ore, 0), (ore, 1), (ore, 2)]),
+                Err(RmrkError::InputsMismatch)
+            );
+            set_caller(bob());
+            assert_eq!(
+                contract.craft(recipe_id, inputs.clone()),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(alice());
+            assert_eq!(contract.craft(recipe_id, inputs), Ok(0));
+
+            assert_eq!(contract.nfts(ore, 0), None);
+            assert_eq!(contract.nfts(wood, 0), None);
+            assert!(contract.nfts(ore, 2).is_some());
+            assert_eq!(mock::owner(swords, 0), Some(account(alice())));
+            assert_eq!(
+                contract.properties(swords, Some(0), b"attack".to_vec()),
+                Some(b"7".to_vec())
+            );
+        }
+
+        #[ink::test]
+        fn recipes_must_consume_and_produce_something() {
+            let mut contract = init_test_contract();
+            let ore = collection_with_nfts(&mut contract, alice(), 1);
+            let swords = collection_with_nfts(&mut contract, alice(), 0);
+            let recipe = Recipe {
+                inputs: vec![(ore, 1)],
+                output: CraftOutput {
+                    collection_id: swords,
+                    metadata: b"sword".to_vec(),
+                    transferable: true,
+                    resources: None,
+                    properties: vec![],
+                },
+                enabled: true,
+            };
+            let recipe_id = contract.add_recipe(recipe.clone()).unwrap();
+
+            let mut no_inputs = recipe.clone();
+            no_inputs.inputs = vec![];
+            let mut zero_count = recipe.clone();
+            zero_count.inputs = vec![(ore, 1), (ore, 0)];
+            let mut empty_output = recipe;
+            empty_output.output.metadata = vec![];
+            for invalid in [no_inputs, zero_count, empty_output] {
+                assert_eq!(
+                    contract.add_recipe(invalid.clone()),
+                    Err(RmrkError::InvalidRecipe)
+                );
+                assert_eq!(
+                    contract.update_recipe(recipe_id, invalid),
+                    Err(RmrkError::InvalidRecipe)
+                );
+            }
+            assert_eq!(contract.recipe(recipe_id).unwrap().inputs, vec![(ore, 1)]);
+        }
+
+        #[ink::test]
+        fn staking_accrues_rewards_per_block() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 2);
+            for nft_id in 0..2 {
+                mock::transfer(collection_id, nft_id, bob());
+                deposit(&mut contract, bob(), collection_id, nft_id);
+            }
+            set_caller(bob());
+            assert_eq!(
+                contract.stake(collection_id, 0),
+                Err(RmrkError::NotStakeable)
+            );
+            set_caller(alice());
+            contract.set_staking_rate(collection_id, Some(10)).unwrap();
+            pay_as(alice(), 100);
+            contract.fund_rewards().unwrap();
+
+            set_caller(bob());
+            contract.stake(collection_id, 0).unwrap();
+            contract.stake(collection_id, 1).unwrap();
+            assert_eq!(
+                contract.withdraw_nft(collection_id, 0),
+                Err(RmrkError::NftLocked)
+            );
+            set_caller(charlie());
+            assert_eq!(
+                contract.unstake(collection_id, 0),
+                Err(RmrkError::NotNftOwner)
+            );
+
+            advance_blocks(3);
+            let staked = vec![(collection_id, 0), (collection_id, 1)];
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 60);
+            set_caller(bob());
+            let bob_balance = balance(bob());
+            assert_eq!(contract.claim_rewards(staked.clone()), Ok(60));
+            assert_eq!(balance(bob()), bob_balance + 60);
+            assert_eq!(contract.reward_pool(), 40);
+
+            advance_blocks(2);
+            contract.unstake(collection_id, 0).unwrap();
+            assert_eq!(mock::owner(collection_id, 0), Some(account(bob())));
+            assert_eq!(contract.escrow(collection_id, 0), None);
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 40);
+            advance_blocks(1);
+            assert_eq!(
+                contract.claim_rewards(vec![(collection_id, 1)]),
+                Err(RmrkError::InsufficientRewards)
+            );
+        }
+
+        #[ink::test]
+        fn staking_rate_changes_only_apply_from_then_on() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 1);
+            mock::transfer(collection_id, 0, bob());
+            deposit(&mut contract, bob(), collection_id, 0);
+            set_caller(alice());
+            contract.set_staking_rate(collection_id, Some(10)).unwrap();
+            set_caller(bob());
+            contract.stake(collection_id, 0).unwrap();
+            let staked = vec![(collection_id, 0)];
+
+            advance_blocks(3);
+            set_caller(alice());
+            contract.set_staking_rate(collection_id, Some(1)).unwrap();
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 30);
+            advance_blocks(2);
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 32);
+
+            contract.set_staking_rate(collection_id, None).unwrap();
+            advance_blocks(2);
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 32);
+            contract.set_staking_rate(collection_id, Some(5)).unwrap();
+            advance_blocks(1);
+            assert_eq!(contract.pending_rewards(bob(), staked.clone()), 37);
+
+            pay_as(alice(), 100);
+            contract.fund_rewards().unwrap();
+            set_caller(bob());
+            assert_eq!(contract.claim_rewards(staked.clone()), Ok(37));
+            assert_eq!(contract.pending_rewards(bob(), staked), 0);
+        }
+
+        #[ink::test]
+        fn rental_nests_into_character_until_returned() {
+            let mut contract = init_test_contract();
+            let items = collection_with_nfts(&mut contract, alice(), 1);
+            let characters = collection_with_nfts(&mut contract, alice(), 1);
+            deposit(&mut contract, alice(), items, 0);
+            mock::transfer(characters, 0, bob());
+            deposit(&mut contract, bob(), characters, 0);
+            set_caller(alice());
+            contract.offer_rental(items, 0, 10, 5).unwrap();
+
+            pay_as(bob(), 60);
+            assert_eq!(
+                contract.rent(items, 0, 6, (characters, 0)),
+                Err(RmrkError::InvalidRentalPeriod)
+            );
+            pay_as(bob(), 20);
+            assert_eq!(
+                contract.rent(items, 0, 3, (characters, 0)),
+                Err(RmrkError::WrongPayment)
+            );
+            let alice_balance = balance(alice());
+            pay_as(bob(), 30);
+            contract.rent(items, 0, 3, (characters, 0)).unwrap();
+            assert_eq!(balance(alice()), alice_balance + 30);
+            assert_eq!(
+                mock::owner(items, 0),
+                Some(AccountIdOrCollectionNftTuple::CollectionAndNftTuple(
+                    characters, 0
+                ))
+            );
+            assert_eq!(contract.nfts(items, 0).map(|nft| nft.pending), Some(false));
+
+            assert_eq!(contract.burn_nft(items, 0, 1), Err(RmrkError::NftRented));
+            assert_eq!(
+                contract.send(items, 0, AccountIdOrCollectionNftTuple::AccountId(bob())),
+                Err(RmrkError::NftRented)
+            );
+            assert_eq!(
+                contract.burn_nft(characters, 0, 2),
+                Err(RmrkError::NftInEscrow)
+            );
+            assert_eq!(
+                contract.withdraw_nft(characters, 0),
+                Err(RmrkError::NftLocked)
+            );
+            set_caller(alice());
+            assert_eq!(
+                contract.cancel_rental_offer(items, 0),
+                Err(RmrkError::NftRented)
+            );
+
+            advance_blocks(2);
+            set_caller(charlie());
+            assert_eq!(
+                contract.end_rental(items, 0),
+                Err(RmrkError::RentalNotExpired)
+            );
+            advance_blocks(1);
+            contract.end_rental(items, 0).unwrap();
+            assert_eq!(mock::owner(items, 0), Some(account(alice())));
+            assert_eq!(contract.rental_offer(items, 0), None);
+            set_caller(bob());
+            contract.withdraw_nft(characters, 0).unwrap();
+            assert_eq!(mock::owner(characters, 0), Some(account(bob())));
+        }
+
+        #[ink::test]
+        fn governance_counts_root_owned_and_delegated_nfts() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 4);
+            mock::transfer(collection_id, 0, bob());
+            mock::transfer(collection_id, 3, charlie());
+            // NFT 2 is nested in bob's NFT 1, so bob root-owns it as well
+            mock::transfer(collection_id, 1, contract_account());
+            mock::transfer(collection_id, 2, contract_account());
+            contract
+                .send(
+                    collection_id,
+                    2,
+                    AccountIdOrCollectionNftTuple::CollectionAndNftTuple(collection_id, 1),
+                )
+                .unwrap();
+            mock::transfer(collection_id, 1, bob());
+            let proposal_id = contract
+                .create_proposal(collection_id, b"fund the guild".to_vec(), 5)
+                .unwrap();
+
+            set_caller(bob());
+            assert_eq!(
+                contract.vote(proposal_id, vec![3], true),
+                Err(RmrkError::NotNftOwner)
+            );
+            assert_eq!(contract.vote(proposal_id, vec![0, 1, 2], true), Ok(3));
+            assert_eq!(
+                contract.vote(proposal_id, vec![0], false),
+                Err(RmrkError::AlreadyVoted)
+            );
+            assert!(contract.has_voted(proposal_id, collection_id, 2));
+
+            set_caller(charlie());
+            contract.delegate(Some(alice())).unwrap();
+            assert_eq!(
+                contract.vote(proposal_id, vec![3], false),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(alice());
+            assert_eq!(contract.vote(proposal_id, vec![3], false), Ok(1));
+            assert_eq!(
+                contract.finalize_proposal(proposal_id),
+                Err(RmrkError::VotingNotEnded)
+            );
+
+            advance_blocks(5);
+            set_caller(charlie());
+            assert_eq!(
+                contract.vote(proposal_id, vec![3], false),
+                Err(RmrkError::VotingClosed)
+            );
+            assert_eq!(contract.finalize_proposal(proposal_id), Ok(true));
+            let proposal = contract.proposal(proposal_id).unwrap();
+            assert_eq!((proposal.votes_for, proposal.votes_against), (3, 1));
+            assert_eq!(proposal.passed, Some(true));
+        }
+
+        #[ink::test]
+        fn badges_are_soulbound_and_revocable() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 0);
+            let badge_type = contract
+                .define_badge_type(collection_id, b"early supporter".to_vec())
+                .unwrap();
+            set_caller(bob());
+            assert_eq!(
+                contract.award_badge(badge_type, bob()),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+            assert_eq!(contract.award_badge(badge_type, bob()), Ok(0));
+            assert_eq!(contract.award_badge(badge_type, bob()), Ok(1));
+            assert_eq!(
+                contract.badges_of(bob()),
+                vec![(collection_id, 0), (collection_id, 1)]
+            );
+            assert_eq!(
+                contract.nfts(collection_id, 0).map(|nft| nft.transferable),
+                Some(false)
+            );
+
+            set_caller(bob());
+            assert_eq!(
+                contract.send(
+                    collection_id,
+                    0,
+                    AccountIdOrCollectionNftTuple::AccountId(bob())
+                ),
+                Err(RmrkError::NotTransferable)
+            );
+            assert_eq!(
+                contract.send_batch(vec![(collection_id, 1, account(bob()))]),
+                Err(RmrkError::NotTransferable)
+            );
+            assert_eq!(
+                contract.burn_nft(collection_id, 0, 1),
+                Err(RmrkError::NotTransferable)
+            );
+            assert_eq!(
+                contract.revoke_badge(collection_id, 0),
+                Err(RmrkError::NotAuthorized)
+            );
+
+            set_caller(alice());
+            contract.revoke_badge(collection_id, 0).unwrap();
+            assert_eq!(contract.nfts(collection_id, 0), None);
+            assert_eq!(contract.badges_of(bob()), vec![(collection_id, 1)]);
+        }
+
+        #[ink::test]
+        fn tickets_check_in_once_and_freeze_after_event() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, alice(), 0);
+            let event_id = contract.create_event(collection_id, 10).unwrap();
+            contract.set_scanner(event_id, charlie(), true).unwrap();
+            let ticket = contract
+                .issue_ticket(event_id, bob(), b"A12".to_vec(), b"vip".to_vec(), vec![])
+                .unwrap();
+            assert_eq!(
+                contract.properties(collection_id, Some(ticket), b"seat".to_vec()),
+                Some(b"A12".to_vec())
+            );
+            assert_eq!(
+                contract.properties(collection_id, Some(ticket), b"tier".to_vec()),
+                Some(b"vip".to_vec())
+            );
+
+            set_caller(bob());
+            assert_eq!(
+                contract.set_property(
+                    collection_id,
+                    Some(ticket),
+                    b"tier".to_vec(),
+                    b"vvip".to_vec()
+                ),
+                Err(RmrkError::TicketImmutable)
+            );
+            assert_eq!(
+                contract.check_in(collection_id, ticket),
+                Err(RmrkError::NotScanner)
+            );
+            set_caller(charlie());
+            contract.check_in(collection_id, ticket).unwrap();
+            assert!(contract.is_checked_in(collection_id, ticket));
+            assert_eq!(
+                contract.check_in(collection_id, ticket),
+                Err(RmrkError::AlreadyCheckedIn)
+            );
+            assert_eq!(
+                contract.set_property(collection_id, Some(ticket), b"used".to_vec(), vec![]),
+                Err(RmrkError::TicketImmutable)
+            );
+
+            set_caller(bob());
+            assert_eq!(
+                contract.deposit_nft(collection_id, ticket),
+                Err(RmrkError::AlreadyCheckedIn)
+            );
+            mock::transfer(collection_id, ticket, contract_account());
+            set_caller(alice());
+            assert_eq!(
+                contract.send(collection_id, ticket, account(alice())),
+                Err(RmrkError::AlreadyCheckedIn)
+            );
+
+            advance_blocks(10);
+            assert_eq!(
+                contract.set_property(collection_id, Some(ticket), b"seat".to_vec(), vec![]),
+                Err(RmrkError::TicketImmutable)
+            );
+            assert_eq!(
+                contract.burn_nft(collection_id, ticket, 1),
+                Err(RmrkError::TicketImmutable)
+            );
+            assert_eq!(
+                contract.issue_ticket(event_id, bob(), vec![], vec![], vec![]),
+                Err(RmrkError::EventEnded)
+            );
+        }
+
+        #[ink::test]
+        fn membership_tier_counts_nested_and_matching_nfts() {
+            let mut contract = init_test_contract();
+            let passes = collection_with_nfts(&mut contract, contract_account(), 3);
+            let others = collection_with_nfts(&mut contract, bob(), 2);
+            contract
+                .set_property(passes, Some(2), b"rank".to_vec(), b"gold".to_vec())
+                .unwrap();
+            // Pass 1 is nested in bob's NFT from an unrelated collection
+            contract
+                .send(
+                    passes,
+                    1,
+                    AccountIdOrCollectionNftTuple::CollectionAndNftTuple(others, 0),
+                )
+                .unwrap();
+            contract.send(passes, 2, account(bob())).unwrap();
+            contract
+                .set_membership(Some(MembershipConfig {
+                    collections: vec![passes],
+                    tiers: vec![
+                        MembershipTier {
+                            name: b"member".to_vec(),
+                            requirement: TierRequirement::Count(1),
+                        },
+                        MembershipTier {
+                            name: b"gold".to_vec(),
+                            requirement: TierRequirement::Property {
+                                key: b"rank".to_vec(),
+                                value: b"gold".to_vec(),
+                                count: 1,
+                            },
+                        },
+                        MembershipTier {
+                            name: b"whale".to_vec(),
+                            requirement: TierRequirement::Count(3),
+                        },
+                    ],
+                }))
+                .unwrap();
+
+            assert_eq!(contract.membership_tier(bob()), Ok(Some(1)));
+            assert_eq!(contract.membership_tier(charlie()), Ok(None));
+            contract.send(passes, 0, account(bob())).unwrap();
+            assert_eq!(contract.membership_tier(bob()), Ok(Some(2)));
+            mock::transfer(others, 0, charlie());
+            assert_eq!(contract.membership_tier(bob()), Ok(Some(1)));
+            assert_eq!(contract.membership_tier(charlie()), Ok(Some(0)));
+
+            set_caller(bob());
+            assert_eq!(
+                contract.set_property(passes, Some(0), b"rank".to_vec(), b"gold".to_vec()),
+                Err(RmrkError::NotAuthorized)
+            );
+            assert_eq!(contract.membership_tier(bob()), Ok(Some(1)));
+            assert_eq!(contract.set_membership(None), Err(RmrkError::NotAuthorized));
+        }
+
+        #[ink::test]
+        fn aggregate_stats_sums_equipped_children() {
+            let mut contract = init_test_contract();
+            let characters = collection_with_nfts(&mut contract, contract_account(), 1);
+            let items = collection_with_nfts(&mut contract, contract_account(), 3);
+            let stat = |contract: &mut RmrkTestContract, c, n, key: &[u8], value: &[u8]| {
+                contract
+                    .set_property(c, Some(n), key.to_vec(), value.to_vec())
+                    .unwrap()
+            };
+            stat(&mut contract, characters, 0, b"attack", b"10");
+            stat(&mut contract, items, 0, b"attack", b"5");
+            stat(&mut contract, items, 0, b"defense", b"-2");
+            stat(&mut contract, items, 1, b"attack", b"7");
+            stat(&mut contract, items, 2, b"attack", b"+3");
+            for nft_id in 0..3 {
+                contract
+                    .send(
+                        items,
+                        nft_id,
+                        AccountIdOrCollectionNftTuple::CollectionAndNftTuple(characters, 0),
+                    )
+                    .unwrap();
+            }
+            // Items 0 and 2 are equipped, item 1 only sits in the inventory
+            mock::with_state(|state| {
+                for nft_id in [0, 2] {
+                    state.nfts.get_mut(&(items, nft_id)).unwrap().equipped = true;
+                }
+            });
+
+            assert_eq!(
+                contract.aggregate_stats(
+                    characters,
+                    0,
+                    vec![b"attack".to_vec(), b"defense".to_vec(), b"speed".to_vec()]
+                ),
+                Ok(vec![15, -2, 0])
+            );
+            assert_eq!(
+                contract.aggregate_stats(characters, 1, vec![]),
+                Err(RmrkError::NftNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn aggregate_stats_counts_every_equipped_child() {
+            let mut contract = init_test_contract();
+            let characters = collection_with_nfts(&mut contract, contract_account(), 1);
+            let items = collection_with_nfts(&mut contract, contract_account(), 2);
+            contract
+                .set_property(characters, Some(0), b"attack".to_vec(), b"10".to_vec())
+                .unwrap();
+            contract
+                .set_property(items, Some(0), b"attack".to_vec(), b"4".to_vec())
+                .unwrap();
+            // A cursed item nobody would list voluntarily
+            contract
+                .set_property(items, Some(1), b"attack".to_vec(), b"-9".to_vec())
+                .unwrap();
+            for nft_id in 0..2 {
+                contract
+                    .send(
+                        items,
+                        nft_id,
+                        AccountIdOrCollectionNftTuple::CollectionAndNftTuple(characters, 0),
+                    )
+                    .unwrap();
+            }
+            mock::with_state(|state| {
+                for nft_id in 0..2 {
+                    state.nfts.get_mut(&(items, nft_id)).unwrap().equipped = true;
+                }
+            });
+
+            assert_eq!(
+                contract.aggregate_stats(characters, 0, vec![b"attack".to_vec()]),
+                Ok(vec![5])
+            );
+        }
+
+        #[test]
+        fn decode_stat_accepts_only_decimal_integers() {
+            assert_eq!(decode_stat(b"42"), Some(42));
+            assert_eq!(decode_stat(b"-42"), Some(-42));
+            assert_eq!(decode_stat(b"-9223372036854775808"), Some(i64::MIN));
+            assert_eq!(decode_stat(b"9223372036854775808"), None);
+            for value in [&b""[..], b"-", b"+1", b" 1", b"1.5", b"0x10"] {
+                assert_eq!(decode_stat(value), None);
+            }
+        }
+
+        #[ink::test]
+        fn grant_xp_adds_and_promotes_level_resources() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 1);
+            contract
+                .set_levels(
+                    collection_id,
+                    vec![
+                        LevelUp {
+                            xp: 100,
+                            resource: basic(b"ipfs://level-1"),
+                        },
+                        LevelUp {
+                            xp: 250,
+                            resource: basic(b"ipfs://level-2"),
+                        },
+                    ],
+                )
+                .unwrap();
+            assert_eq!(
+                contract.grant_xp(collection_id, 0, 50),
+                Err(RmrkError::NotGameServer)
+            );
+            contract.set_game_server(charlie(), true).unwrap();
+
+            set_caller(charlie());
+            assert_eq!(contract.grant_xp(collection_id, 0, 50), Ok(0));
+            assert_eq!(
+                contract.aggregate_stats(collection_id, 0, vec![b"xp".to_vec()]),
+                Ok(vec![50])
+            );
+            assert_eq!(contract.grant_xp(collection_id, 0, 60), Ok(1));
+            assert_eq!(contract.priorities(collection_id, 0, 0), Some(0));
+            assert_eq!(contract.grant_xp(collection_id, 0, 10), Ok(1));
+            assert_eq!(contract.next_resource_id(collection_id, 0), 1);
+
+            assert_eq!(contract.grant_xp(collection_id, 0, 140), Ok(2));
+            assert_eq!(contract.priorities(collection_id, 0, 1), Some(0));
+            assert_eq!(contract.priorities(collection_id, 0, 0), Some(1));
+            assert_eq!(
+                contract.properties(collection_id, Some(0), b"level".to_vec()),
+                Some(b"2".to_vec())
+            );
+
+            assert_eq!(
+                contract.set_property(collection_id, Some(0), b"xp".to_vec(), b"9999".to_vec()),
+                Err(RmrkError::NotAuthorized)
+            );
+            assert_eq!(
+                contract.add_basic_resource(collection_id, 0, basic(b"ipfs://level-9")),
+                Err(RmrkError::NotAuthorized)
+            );
+            assert_eq!(
+                contract.set_priority(collection_id, 0, vec![0, 1]),
+                Err(RmrkError::NotAuthorized)
+            );
+            assert_eq!(contract.priorities(collection_id, 0, 1), Some(0));
+        }
+
+        #[ink::test]
+        fn xp_is_an_ordinary_property_without_levels() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 1);
+            assert_eq!(
+                contract.set_property(collection_id, Some(0), b"xp".to_vec(), b"10".to_vec()),
+                Ok(())
+            );
+            assert_eq!(
+                contract.properties(collection_id, Some(0), b"xp".to_vec()),
+                Some(b"10".to_vec())
+            );
+        }
+
+        #[ink::test]
+        fn apply_schedule_promotes_active_resource() {
+            let mut contract = init_test_contract();
+            let collection_id = collection_with_nfts(&mut contract, contract_account(), 3);
+            for nft_id in 0..2 {
+                for src in [&b"ipfs://day"[..], b"ipfs://night"] {
+                    contract
+                        .add_basic_resource(collection_id, nft_id, basic(src))
+                        .unwrap();
+                }
+            }
+            mock::transfer(collection_id, 1, bob());
+            let entry = |start_block, end_block, resource_id| ScheduleEntry {
+                start_block,
+                end_block,
+                resource_id,
+            };
+            assert_eq!(
+                contract.set_resource_schedule(
+                    collection_id,
+                    Some(ResourceSchedule {
+                        entries: vec![entry(5, 5, 1)]
+                    })
+                ),
+                Err(RmrkError::InvalidEndBlock)
+            );
+            contract
+                .set_resource_schedule(
+                    collection_id,
+                    Some(ResourceSchedule {
+                        entries: vec![entry(0, 5, 0), entry(5, 10, 1)],
+                    }),
+                )
+                .unwrap();
+
+            set_caller(charlie());
+            assert_eq!(contract.active_resource(collection_id), Some(0));
+            assert_eq!(contract.apply_schedule(collection_id, vec![0, 1, 2]), Ok(0));
+            advance_blocks(5);
+            assert_eq!(contract.active_resource(collection_id), Some(1));
+            assert_eq!(contract.apply_schedule(collection_id, vec![0, 1, 2]), Ok(1));
+            assert_eq!(contract.priorities(collection_id, 0, 1), Some(0));
+            assert_eq!(contract.priorities(collection_id, 1, 1), None);
+            assert_eq!(contract.apply_schedule(collection_id, vec![0]), Ok(0));
+            advance_blocks(5);
+            assert_eq!(contract.active_resource(collection_id), None);
+        }
+
+        #[ink::test]
+        fn bundles_move_as_one_and_unbundle_to_holder() {
+            let mut contract = init_test_contract();
+            let items = collection_with_nfts(&mut contract, alice(), 2);
+            let bundles = collection_with_nfts(&mut contract, alice(), 0);
+            assert_eq!(
+                contract.bundle(vec![(items, 0)]),
+                Err(RmrkError::BundlesNotConfigured)
+            );
+            let foreign = collection_with_nfts(&mut contract, alice(), 0);
+            mock::with_state(|state| {
+                state.collections.get_mut(&foreign).unwrap().issuer = alice();
+            });
+            assert_eq!(
+                contract.set_bundle_collection(foreign),
+                Err(RmrkError::NotAuthorized)
+            );
+            contract.set_bundle_collection(bundles).unwrap();
+            for nft_id in 0..2 {
+                mock::transfer(items, nft_id, bob());
+                deposit(&mut contract, bob(), items, nft_id);
+            }
+
+            set_caller(bob());
+            let (bundles, container) = contract.bundle(vec![(items, 0), (items, 1)]).unwrap();
+            assert_eq!(mock::owner(bundles, container), Some(account(bob())));
+            let nested = AccountIdOrCollectionNftTuple::CollectionAndNftTuple(bundles, container);
+            assert_eq!(mock::owner(items, 0), Some(nested.clone()));
+            assert_eq!(contract.nfts(items, 1).map(|nft| nft.pending), Some(false));
+            assert_eq!(contract.escrow(items, 0), None);
+
+            // The container sells like any other NFT, children included
+            deposit(&mut contract, bob(), bundles, container);
+            set_caller(bob());
+            assert_eq!(
+                contract.send(items, 0, account(charlie())),
+                Err(RmrkError::NftInEscrow)
+            );
+            contract.list(bundles, container, 100).unwrap();
+            pay_as(charlie(), 100);
+            contract.buy(bundles, container).unwrap();
+            assert_eq!(
+                mock::with_state(|state| state.root_owner(items, 1)),
+                Some(charlie())
+            );
+
+            // Moving new bundles elsewhere leaves this one intact
+            let next_bundles = collection_with_nfts(&mut contract, alice(), 0);
+            set_caller(alice());
+            contract.set_bundle_collection(next_bundles).unwrap();
+            assert_eq!(contract.bundle_items(next_bundles, container), vec![]);
+
+            deposit(&mut contract, charlie(), bundles, container);
+            set_caller(bob());
+            assert_eq!(
+                contract.unbundle(bundles, container),
+                Err(RmrkError::NotNftOwner)
+            );
+            set_caller(charlie());
+            contract.unbundle(bundles, container).unwrap();
+            assert_eq!(mock::owner(items, 0), Some(account(charlie())));
+            assert_eq!(mock::owner(items, 1), Some(account(charlie())));
+            assert_eq!(contract.nfts(bundles, container), None);
+            assert_eq!(
+                contract.unbundle(bundles, container),
+                Err(RmrkError::NotBundle)
+            );
+        }
+
+        #[ink::test]
+        fn migrate_copies_nfts_and_burns_originals() {
+            let mut contract = init_test_contract();
+            let old = collection_with_nfts(&mut contract, contract_account(), 0);
+            let new = collection_with_nfts(&mut contract, contract_account(), 0);
+            let parents = collection_with_nfts(&mut contract, contract_account(), 1);
+            contract
+                .mint_ntf(
+                    bob(),
+                    old,
+                    Some(charlie()),
+                    Some(500),
+                    b"sword".to_vec(),
+                    true,
+                    None,
+                )
+                .unwrap();
+            contract
+                .mint_ntf_directly_to_nft(
+                    (parents, 0),
+                    old,
+                    None,
+                    None,
+                    b"shield".to_vec(),
+                    true,
+                    None,
+                )
+                .unwrap();
+            contract
+                .mint_ntf(bob(), old, None, None, b"listed".to_vec(), true, None)
+                .unwrap();
+            deposit(&mut contract, bob(), old, 0);
+            deposit(&mut contract, bob(), old, 2);
+            set_caller(bob());
+            contract.list(old, 2, 10).unwrap();
+            set_caller(alice());
+            for src in [&b"ipfs://front"[..], b"ipfs://back"] {
+                contract.add_basic_resource(old, 0, basic(src)).unwrap();
+            }
+            contract.remove_resource(old, 0, 0).unwrap();
+            contract
+                .add_basic_resource(old, 0, basic(b"ipfs://side"))
+                .unwrap();
+            contract.set_priority(old, 0, vec![2, 1]).unwrap();
+            contract
+                .set_property(old, Some(0), b"attack".to_vec(), b"7".to_vec())
+                .unwrap();
+            contract
+                .set_property(old, Some(0), b"scratch".to_vec(), b"x".to_vec())
+                .unwrap();
+            contract
+                .set_migration_keys(old, vec![b"attack".to_vec(), b"scratch-free".to_vec()])
+                .unwrap();
+
+            set_caller(bob());
+            assert_eq!(
+                contract.migrate(old, new, vec![0]),
+                Err(RmrkError::NotAuthorized)
+            );
+            set_caller(alice());
+            assert_eq!(
+                contract.migrate(old, new, vec![2]),
+                Err(RmrkError::NftLocked)
+            );
+            // NFT 3 holds NFT 4, and NFT 5 sits in bob's deposited character
+            let characters = collection_with_nfts(&mut contract, bob(), 1);
+            contract
+                .mint_ntf(contract_account(), old, None, None, vec![], true, None)
+                .unwrap();
+            for parent in [(old, 3), (characters, 0)] {
+                contract
+                    .mint_ntf_directly_to_nft(parent, old, None, None, vec![], true, None)
+                    .unwrap();
+            }
+            deposit(&mut contract, bob(), characters, 0);
+            set_caller(alice());
+            assert_eq!(
+                contract.migrate(old, new, vec![3]),
+                Err(RmrkError::ErrorCode(RmrkErrorCode::Failed))
+            );
+            assert_eq!(
+                contract.migrate(old, new, vec![5]),
+                Err(RmrkError::NftInEscrow)
+            );
+            assert_eq!(contract.next_nft_id(new), 0);
+            assert!(contract.nfts(old, 3).is_some());
+            assert_eq!(contract.migrate(old, new, vec![0, 1]), Ok(vec![0, 1]));
+
+            assert_eq!(contract.nfts(old, 0), None);
+            assert_eq!(contract.nfts(old, 1), None);
+            assert_eq!(contract.escrow(old, 0), None);
+            assert_eq!(contract.migrated_to(old, 0), Some((new, 0)));
+            let sword = contract.nfts(new, 0).unwrap();
+            assert_eq!(sword.owner, account(bob()));
+            assert_eq!(sword.metadata, b"sword".to_vec());
+            assert_eq!(
+                sword.royalty,
+                Some(RoyaltyInfo {
+                    recipient: charlie(),
+                    amount: 500
+                })
+            );
+            assert_eq!(
+                contract.resources(new, 0, 0).map(|info| info.resources),
+                Some(basic_resource(b"ipfs://back"))
+            );
+            assert_eq!(contract.priorities(new, 0, 1), Some(0));
+            assert_eq!(contract.priorities(new, 0, 0), Some(1));
+            assert_eq!(
+                contract.properties(new, Some(0), b"attack".to_vec()),
+                Some(b"7".to_vec())
+            );
+            assert_eq!(contract.properties(new, Some(0), b"scratch".to_vec()), None);
+            assert_eq!(
+                mock::owner(new, 1),
+                Some(AccountIdOrCollectionNftTuple::CollectionAndNftTuple(
+                    parents, 0
+                ))
+            );
         }
     }
 }
@@ -0,0 +1,68 @@
+//! Merkle proofs for mint allowlists.
+//!
+//! Leaves are the keccak-256 hash of the SCALE encoded `(AccountId, u32)` pair of an allowlisted
+//! account and the number of NFTs it may claim. Inner nodes hash their two children in ascending
+//! order, so proofs need no left/right flags.
+
+use ink_env::hash::Keccak256;
+use ink_env::AccountId;
+
+pub type Node = [u8; 32];
+
+pub fn leaf(account: &AccountId, allowance: u32) -> Node {
+    let mut output = Node::default();
+    ink_env::hash_encoded::<Keccak256, _>(&(account, allowance), &mut output);
+    output
+}
+
+pub fn hash_pair(a: &Node, b: &Node) -> Node {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut input = [0u8; 64];
+    input[..32].copy_from_slice(first);
+    input[32..].copy_from_slice(second);
+    let mut output = Node::default();
+    ink_env::hash_bytes::<Keccak256>(&input, &mut output);
+    output
+}
+
+/// Returns whether `proof` leads from `leaf` up to `root`.
+pub fn verify(root: &Node, leaf: Node, proof: &[Node]) -> bool {
+    let computed = proof
+        .iter()
+        .fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(byte: u8) -> AccountId {
+        AccountId::from([byte; 32])
+    }
+
+    #[test]
+    fn verifies_proofs_of_every_leaf() {
+        let leaves = [
+            leaf(&account(1), 2),
+            leaf(&account(2), 1),
+            leaf(&account(3), 5),
+        ];
+        let left = hash_pair(&leaves[0], &leaves[1]);
+        let root = hash_pair(&left, &leaves[2]);
+
+        assert!(verify(&root, leaves[0], &[leaves[1], leaves[2]]));
+        assert!(verify(&root, leaves[1], &[leaves[0], leaves[2]]));
+        assert!(verify(&root, leaves[2], &[left]));
+    }
+
+    #[test]
+    fn rejects_wrong_allowance_or_proof() {
+        let leaves = [leaf(&account(1), 2), leaf(&account(2), 1)];
+        let root = hash_pair(&leaves[0], &leaves[1]);
+
+        assert!(!verify(&root, leaf(&account(1), 3), &[leaves[1]]));
+        assert!(!verify(&root, leaves[0], &[leaves[0]]));
+        assert!(!verify(&root, leaves[0], &[]));
+    }
+}
@@ -0,0 +1,554 @@
+//! In-memory stand-in for the RMRK chain extension used by the off-chain unit tests.
+//!
+//! Every write call is dispatched as if the contract account were the signed origin, which is how
+//! the runtime chain extension forwards calls to the RMRK pallet. Only the permission rules the
+//! contract relies on are modelled.
+
+use crate::types::*;
+use crate::RmrkError;
+use ink_env::test::ChainExtension;
+use ink_env::AccountId;
+use ink_prelude::vec::Vec;
+use scale::{Decode, Encode};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+type MockResult = Result<(), ()>;
+
+#[derive(Default)]
+pub struct MockRmrk {
+    pub contract: AccountId,
+    pub collection_index: CollectionId,
+    pub collections: BTreeMap<CollectionId, CollectionInfo>,
+    pub next_nft_ids: BTreeMap<CollectionId, NftId>,
+    pub nfts: BTreeMap<(CollectionId, NftId), NftInfo>,
+    pub children: BTreeMap<(CollectionId, NftId), BTreeSet<(CollectionId, NftId)>>,
+    pub next_resource_ids: BTreeMap<(CollectionId, NftId), ResourceId>,
+    pub resources: BTreeMap<(CollectionId, NftId, ResourceId), ResourceInfo>,
+    pub priorities: BTreeMap<(CollectionId, NftId), Vec<ResourceId>>,
+    pub properties: BTreeMap<(CollectionId, Option<NftId>, Vec<u8>), Vec<u8>>,
+}
+
+thread_local! {
+    static STATE: RefCell<MockRmrk> = RefCell::new(MockRmrk::default());
+}
+
+/// Resets the mock state and registers every RMRK chain extension function.
+pub fn register_chain_extension(contract: AccountId) {
+    STATE.with(|state| {
+        *state.borrow_mut() = MockRmrk {
+            contract,
+            ..Default::default()
+        }
+    });
+    for func_id in 3501..=3530 {
+        ink_env::test::register_chain_extension(MockExtension { func_id });
+    }
+}
+
+/// Gives the test direct access to the mocked pallet storage.
+pub fn with_state<R>(f: impl FnOnce(&mut MockRmrk) -> R) -> R {
+    STATE.with(|state| f(&mut state.borrow_mut()))
+}
+
+/// Simulates an account calling the pallet's `send` extrinsic directly.
+pub fn transfer(collection_id: CollectionId, nft_id: NftId, new_owner: AccountId) {
+    with_state(|state| {
+        state.unlink_parent(collection_id, nft_id);
+        let nft = state.nfts.get_mut(&(collection_id, nft_id)).unwrap();
+        nft.owner = AccountIdOrCollectionNftTuple::AccountId(new_owner);
+        nft.pending = false;
+    })
+}
+
+/// Returns the direct owner of an NFT.
+pub fn owner(collection_id: CollectionId, nft_id: NftId) -> Option<AccountIdOrCollectionNftTuple> {
+    with_state(|state| {
+        state
+            .nfts
+            .get(&(collection_id, nft_id))
+            .map(|nft| nft.owner.clone())
+    })
+}
+
+struct MockExtension {
+    func_id: u32,
+}
+
+impl ChainExtension for MockExtension {
+    fn func_id(&self) -> u32 {
+        self.func_id
+    }
+
+    fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+        let input = Vec::<u8>::decode(&mut &input[..]).expect("input is a byte vector");
+        let mut input = &input[..];
+        let input = &mut input;
+        with_state(|state| match self.func_id {
+            3501 => read(output, state.next_nft_id(decode(input))),
+            3502 => read(output, state.collection_index),
+            3503 => {
+                let (collection_id, nft_id) = decode(input);
+                read(output, state.next_resource_id(collection_id, nft_id))
+            }
+            3504 => read(output, state.collections.get(&decode(input)).cloned()),
+            3505 => {
+                let key: (CollectionId, NftId) = decode(input);
+                read(output, state.nfts.get(&key).cloned())
+            }
+            3506 => {
+                let (collection_id, nft_id, resource_id) = decode(input);
+                read(output, state.priority(collection_id, nft_id, resource_id))
+            }
+            3507 => {
+                let (parent, child) = decode(input);
+                let found = state
+                    .children
+                    .get(&parent)
+                    .is_some_and(|children| children.contains(&child));
+                read(output, if found { Some(()) } else { None })
+            }
+            3508 => {
+                let key: (CollectionId, NftId, ResourceId) = decode(input);
+                read(output, state.resources.get(&key).cloned())
+            }
+            3511 => {
+                let key: (CollectionId, Option<NftId>, Vec<u8>) = decode(input);
+                read(output, state.properties.get(&key).cloned())
+            }
+            3513 => {
+                let (owner, collection_id, recipient, royalty, metadata, transferable, resources) =
+                    decode(input);
+                write(
+                    output,
+                    state.mint(
+                        AccountIdOrCollectionNftTuple::AccountId(owner),
+                        collection_id,
+                        recipient,
+                        royalty,
+                        metadata,
+                        transferable,
+                        resources,
+                    ),
+                )
+            }
+            3514 => {
+                let (owner, collection_id, recipient, royalty, metadata, transferable, resources): (
+                    (CollectionId, NftId),
+                    _,
+                    _,
+                    _,
+                    _,
+                    _,
+                    _,
+                ) = decode(input);
+                write(
+                    output,
+                    state.mint(
+                        AccountIdOrCollectionNftTuple::CollectionAndNftTuple(owner.0, owner.1),
+                        collection_id,
+                        recipient,
+                        royalty,
+                        metadata,
+                        transferable,
+                        resources,
+                    ),
+                )
+            }
+            3515 => {
+                let (metadata, max, symbol) = decode(input);
+                write(output, state.create_collection(metadata, max, symbol))
+            }
+            3516 => {
+                let (collection_id, nft_id, max_burns) = decode(input);
+                write(output, state.burn(collection_id, nft_id, max_burns))
+            }
+            3518 => {
+                let (collection_id, nft_id, new_owner) = decode(input);
+                write(output, state.send(collection_id, nft_id, new_owner))
+            }
+            3519 => {
+                let (collection_id, nft_id, new_owner) = decode(input);
+                write(output, state.accept_nft(collection_id, nft_id, new_owner))
+            }
+            3522 => {
+                let (collection_id, nft_id, key, value) = decode(input);
+                write(
+                    output,
+                    state.set_property(collection_id, nft_id, key, value),
+                )
+            }
+            3524 => {
+                let (collection_id, nft_id, resource) = decode(input);
+                write(
+                    output,
+                    state.add_resource(collection_id, nft_id, ResourceTypes::Basic(resource)),
+                )
+            }
+            3525 => {
+                let (collection_id, nft_id, resource) = decode(input);
+                write(
+                    output,
+                    state.add_resource(collection_id, nft_id, ResourceTypes::Composable(resource)),
+                )
+            }
+            3526 => {
+                let (collection_id, nft_id, resource) = decode(input);
+                write(
+                    output,
+                    state.add_resource(collection_id, nft_id, ResourceTypes::Slot(resource)),
+                )
+            }
+            3528 => {
+                let (collection_id, nft_id, resource_id) = decode(input);
+                write(
+                    output,
+                    state.remove_resource(collection_id, nft_id, resource_id),
+                )
+            }
+            3530 => {
+                let (collection_id, nft_id, priorities) = decode(input);
+                write(
+                    output,
+                    state.set_priority(collection_id, nft_id, priorities),
+                )
+            }
+            func_id => panic!("unknown RMRK chain extension function {}", func_id),
+        })
+    }
+}
+
+fn decode<T: Decode>(input: &mut &[u8]) -> T {
+    T::decode(input).expect("chain extension input decodes")
+}
+
+fn read<T: Encode>(output: &mut Vec<u8>, value: T) -> u32 {
+    value.encode_to(output);
+    0
+}
+
+fn write(output: &mut Vec<u8>, result: MockResult) -> u32 {
+    match result {
+        Ok(()) => read(output, Ok::<(), RmrkError>(())),
+        Err(()) => 1,
+    }
+}
+
+fn ensure(condition: bool) -> MockResult {
+    if condition {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+impl MockRmrk {
+    fn next_nft_id(&self, collection_id: CollectionId) -> NftId {
+        self.next_nft_ids.get(&collection_id).copied().unwrap_or(0)
+    }
+
+    fn next_resource_id(&self, collection_id: CollectionId, nft_id: NftId) -> ResourceId {
+        self.next_resource_ids
+            .get(&(collection_id, nft_id))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn priority(
+        &self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        resource_id: ResourceId,
+    ) -> Option<u32> {
+        self.priorities
+            .get(&(collection_id, nft_id))?
+            .iter()
+            .position(|id| *id == resource_id)
+            .map(|position| position as u32)
+    }
+
+    pub fn root_owner(&self, collection_id: CollectionId, nft_id: NftId) -> Option<AccountId> {
+        let mut current = (collection_id, nft_id);
+        loop {
+            match &self.nfts.get(&current)?.owner {
+                AccountIdOrCollectionNftTuple::AccountId(account) => return Some(*account),
+                AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => current = (*c, *n),
+            }
+        }
+    }
+
+    fn is_issuer(&self, collection_id: CollectionId) -> bool {
+        self.collections
+            .get(&collection_id)
+            .is_some_and(|collection| collection.issuer == self.contract)
+    }
+
+    fn is_root_owner(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+        self.root_owner(collection_id, nft_id) == Some(self.contract)
+    }
+
+    fn is_descendant(
+        &self,
+        ancestor: (CollectionId, NftId),
+        mut nft: (CollectionId, NftId),
+    ) -> bool {
+        loop {
+            if nft == ancestor {
+                return true;
+            }
+            match self.nfts.get(&nft).map(|info| &info.owner) {
+                Some(AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n)) => nft = (*c, *n),
+                _ => return false,
+            }
+        }
+    }
+
+    fn unlink_parent(&mut self, collection_id: CollectionId, nft_id: NftId) {
+        if let Some(AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n)) = self
+            .nfts
+            .get(&(collection_id, nft_id))
+            .map(|nft| nft.owner.clone())
+        {
+            if let Some(children) = self.children.get_mut(&(c, n)) {
+                children.remove(&(collection_id, nft_id));
+            }
+        }
+    }
+
+    fn mint(
+        &mut self,
+        owner: AccountIdOrCollectionNftTuple,
+        collection_id: CollectionId,
+        royalty_recipient: Option<AccountId>,
+        royalty: Option<u32>,
+        metadata: Vec<u8>,
+        transferable: bool,
+        resources: Option<Vec<ResourceTypes>>,
+    ) -> MockResult {
+        ensure(self.is_issuer(collection_id))?;
+        let collection = self.collections.get_mut(&collection_id).ok_or(())?;
+        ensure(collection.max.is_none_or(|max| collection.nfts_count < max))?;
+        if let AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) = owner {
+            ensure(self.nfts.contains_key(&(c, n)))?;
+        }
+        let collection = self.collections.get_mut(&collection_id).unwrap();
+        collection.nfts_count += 1;
+        let nft_id = self.next_nft_id(collection_id);
+        self.next_nft_ids.insert(collection_id, nft_id + 1);
+        if let AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) = owner {
+            self.children
+                .entry((c, n))
+                .or_default()
+                .insert((collection_id, nft_id));
+        }
+        let royalty = royalty.map(|amount| RoyaltyInfo {
+            recipient: royalty_recipient.unwrap_or(self.contract),
+            amount,
+        });
+        self.nfts.insert(
+            (collection_id, nft_id),
+            NftInfo {
+                owner,
+                royalty,
+                metadata,
+                equipped: false,
+                pending: false,
+                transferable,
+            },
+        );
+        for resource in resources.unwrap_or_default() {
+            self.insert_resource(collection_id, nft_id, resource, false);
+        }
+        Ok(())
+    }
+
+    fn create_collection(
+        &mut self,
+        metadata: Vec<u8>,
+        max: Option<u32>,
+        symbol: Vec<u8>,
+    ) -> MockResult {
+        let collection_id = self.collection_index;
+        self.collection_index += 1;
+        self.collections.insert(
+            collection_id,
+            CollectionInfo {
+                issuer: self.contract,
+                metadata,
+                max,
+                symbol,
+                nfts_count: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn burn(&mut self, collection_id: CollectionId, nft_id: NftId, max_burns: u32) -> MockResult {
+        ensure(self.is_root_owner(collection_id, nft_id))?;
+        ensure(self.subtree_size(collection_id, nft_id) <= max_burns)?;
+        self.unlink_parent(collection_id, nft_id);
+        self.burn_recursively(collection_id, nft_id);
+        Ok(())
+    }
+
+    fn subtree_size(&self, collection_id: CollectionId, nft_id: NftId) -> u32 {
+        1 + self
+            .children
+            .get(&(collection_id, nft_id))
+            .map_or(0, |children| {
+                children
+                    .iter()
+                    .map(|(c, n)| self.subtree_size(*c, *n))
+                    .sum()
+            })
+    }
+
+    fn burn_recursively(&mut self, collection_id: CollectionId, nft_id: NftId) {
+        for (c, n) in self
+            .children
+            .remove(&(collection_id, nft_id))
+            .unwrap_or_default()
+        {
+            self.burn_recursively(c, n);
+        }
+        if self.nfts.remove(&(collection_id, nft_id)).is_some() {
+            if let Some(collection) = self.collections.get_mut(&collection_id) {
+                collection.nfts_count -= 1;
+            }
+        }
+        self.resources
+            .retain(|(c, n, _), _| (*c, *n) != (collection_id, nft_id));
+        self.priorities.remove(&(collection_id, nft_id));
+        self.properties
+            .retain(|(c, n, _), _| (*c, *n) != (collection_id, Some(nft_id)));
+    }
+
+    fn send(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        new_owner: AccountIdOrCollectionNftTuple,
+    ) -> MockResult {
+        let nft = self.nfts.get(&(collection_id, nft_id)).ok_or(())?;
+        ensure(nft.transferable)?;
+        ensure(self.is_root_owner(collection_id, nft_id))?;
+        let pending = match new_owner {
+            AccountIdOrCollectionNftTuple::AccountId(_) => false,
+            AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => {
+                ensure(self.nfts.contains_key(&(c, n)))?;
+                ensure(!self.is_descendant((collection_id, nft_id), (c, n)))?;
+                !self.is_root_owner(c, n)
+            }
+        };
+        self.unlink_parent(collection_id, nft_id);
+        if let AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) = new_owner {
+            self.children
+                .entry((c, n))
+                .or_default()
+                .insert((collection_id, nft_id));
+        }
+        let nft = self.nfts.get_mut(&(collection_id, nft_id)).unwrap();
+        nft.owner = new_owner;
+        nft.pending = pending;
+        nft.equipped = false;
+        Ok(())
+    }
+
+    fn accept_nft(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        new_owner: AccountIdOrCollectionNftTuple,
+    ) -> MockResult {
+        let nft = self.nfts.get(&(collection_id, nft_id)).ok_or(())?;
+        ensure(nft.pending && nft.owner == new_owner)?;
+        match new_owner {
+            AccountIdOrCollectionNftTuple::CollectionAndNftTuple(c, n) => {
+                ensure(self.is_root_owner(c, n))?
+            }
+            AccountIdOrCollectionNftTuple::AccountId(_) => return Err(()),
+        }
+        self.nfts.get_mut(&(collection_id, nft_id)).unwrap().pending = false;
+        Ok(())
+    }
+
+    fn set_property(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: Option<NftId>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> MockResult {
+        ensure(self.is_issuer(collection_id))?;
+        if let Some(nft_id) = nft_id {
+            ensure(self.nfts.contains_key(&(collection_id, nft_id)))?;
+        }
+        self.properties.insert((collection_id, nft_id, key), value);
+        Ok(())
+    }
+
+    fn insert_resource(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        resource: ResourceTypes,
+        pending: bool,
+    ) {
+        let id = self.next_resource_id(collection_id, nft_id);
+        self.next_resource_ids
+            .insert((collection_id, nft_id), id + 1);
+        self.resources.insert(
+            (collection_id, nft_id, id),
+            ResourceInfo {
+                id,
+                resources: resource,
+                pending,
+                pending_removal: false,
+            },
+        );
+    }
+
+    fn add_resource(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        resource: ResourceTypes,
+    ) -> MockResult {
+        ensure(self.is_issuer(collection_id))?;
+        ensure(self.nfts.contains_key(&(collection_id, nft_id)))?;
+        let pending = !self.is_root_owner(collection_id, nft_id);
+        self.insert_resource(collection_id, nft_id, resource, pending);
+        Ok(())
+    }
+
+    fn remove_resource(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        resource_id: ResourceId,
+    ) -> MockResult {
+        ensure(self.is_issuer(collection_id))?;
+        let owned = self.is_root_owner(collection_id, nft_id);
+        let resource = self
+            .resources
+            .get_mut(&(collection_id, nft_id, resource_id))
+            .ok_or(())?;
+        if owned {
+            self.resources.remove(&(collection_id, nft_id, resource_id));
+        } else {
+            resource.pending_removal = true;
+        }
+        Ok(())
+    }
+
+    fn set_priority(
+        &mut self,
+        collection_id: CollectionId,
+        nft_id: NftId,
+        priorities: Vec<ResourceId>,
+    ) -> MockResult {
+        ensure(self.is_root_owner(collection_id, nft_id))?;
+        self.priorities.insert((collection_id, nft_id), priorities);
+        Ok(())
+    }
+}
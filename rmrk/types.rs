@@ -1,18 +1,31 @@
-use ink_env::AccountId;
-use ink_prelude::vec::Vec;
+use ink_env::{AccountId, DefaultEnvironment, Environment};
+use ink_prelude::{format, vec::Vec};
+use ink_storage::traits::{PackedLayout, SpreadLayout};
 use scale::{Decode, Encode};
 
+#[cfg(feature = "std")]
+use ink_storage::traits::StorageLayout;
+
 pub type CollectionId = u32;
 pub type NftId = u32;
 pub type ResourceId = u32;
 pub type BaseId = u32;
 pub type SlotId = u32;
 pub type PartId = u32;
+pub type Balance = <DefaultEnvironment as Environment>::Balance;
+pub type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+pub type OfferId = u32;
+pub type SwapId = u32;
+pub type PhaseId = u32;
+pub type RecipeId = u32;
+pub type ProposalId = u32;
+pub type BadgeTypeId = u32;
+pub type EventId = u32;
 
 #[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub struct CollectionInfo {
-    /// Current bidder and bid price.
+    /// Account allowed to mint into and manage the collection
     pub issuer: AccountId,
 
     pub metadata: Vec<u8>,
@@ -53,31 +66,68 @@ pub enum AccountIdOrCollectionNftTuple {
 pub struct RoyaltyInfo {
     /// Recipient (AccountId) of the royalty
     pub recipient: AccountId,
-    /// Amount (Permill) of the royalty
+    /// Amount (Permill) of the royalty, i.e. parts per million of the sale price. Values above
+    /// 1_000_000 are treated as 100%.
     pub amount: u32,
 }
 
+impl RoyaltyInfo {
+    /// Returns the royalty share of `sale_price`, rounded down.
+    ///
+    /// Splits the price at the Permill boundary so the multiplication cannot overflow.
+    pub fn share_of(&self, sale_price: Balance) -> Balance {
+        const PER_MILL: Balance = 1_000_000;
+        let parts = Balance::from(self.amount).min(PER_MILL);
+        sale_price / PER_MILL * parts + sale_price % PER_MILL * parts / PER_MILL
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct MintTreeSpec {
+    /// Collection the NFT is minted into
+    pub collection_id: CollectionId,
+    pub royalty_recipient: Option<AccountId>,
+    pub royalty: Option<u32>,
+    pub metadata: Vec<u8>,
+    pub transferable: bool,
+    pub resources: Option<Vec<ResourceTypes>>,
+
+    /// NFTs minted directly into this one once it exists
+    pub children: Vec<MintTreeSpec>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct NftIdTree {
+    pub collection_id: CollectionId,
+    pub nft_id: NftId,
+
+    /// Ids of the children, in the order of the spec they were minted from
+    pub children: Vec<NftIdTree>,
+}
+
 #[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 pub struct ResourceInfo {
     pub id: ResourceId,
 
-    resources: ResourceTypes,
+    pub resources: ResourceTypes,
 
     pub pending: bool,
     pub pending_removal: bool,
 }
 
-#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
 pub enum ResourceTypes {
     Basic(BasicResource),
     Composable(ComposableResource),
     Slot(SlotResource),
 }
 
-#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
 pub struct BasicResource {
     /// If the resource is Media, the base property is absent. Media src should be a URI like an
     /// IPFS hash.
@@ -98,8 +148,8 @@ pub struct BasicResource {
     pub thumb: Option<Vec<u8>>,
 }
 
-#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
 pub struct ComposableResource {
     /// If a resource is composed, it will have an array of parts that compose it
     pub parts: Vec<PartId>,
@@ -136,8 +186,8 @@ pub struct ComposableResource {
     pub thumb: Option<Vec<u8>>,
 }
 
-#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
-#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
 pub struct SlotResource {
     /// A Base is uniquely identified by the combination of the word `base`, its minting block
     /// number, and user provided symbol during Base creation, glued by dashes `-`, e.g.
@@ -169,3 +219,503 @@ pub struct SlotResource {
     /// image that is lighter and faster to load but representative of this resource.
     pub thumb: Option<Vec<u8>>,
 }
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Escrow {
+    /// Account that deposited the NFT and gets it back on withdrawal
+    pub depositor: AccountId,
+    /// Set while a listing or other contract feature relies on the NFT staying in escrow
+    pub locked: bool,
+    /// Block the deposit was registered in
+    pub registered_at: BlockNumber,
+    /// Set once the depositor confirmed, in the registration block, that the contract holds the
+    /// NFT
+    pub confirmed: bool,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Listing {
+    pub seller: AccountId,
+    pub price: Balance,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Auction {
+    pub seller: AccountId,
+    /// Lowest acceptable first bid
+    pub reserve_price: Balance,
+    /// Amount every bid must add on top of the current highest bid
+    pub min_increment: Balance,
+    /// First block at which no more bids are accepted
+    pub end_block: BlockNumber,
+    /// A bid placed fewer than this many blocks before the end moves the end to this many blocks
+    /// after the bid
+    pub extension_window: BlockNumber,
+    /// Current bidder and bid price
+    pub highest_bid: Option<(AccountId, Balance)>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum PriceDecay {
+    /// The price drops a little every block
+    Linear,
+    /// The price drops once every `step_blocks` blocks
+    Stepwise { step_blocks: BlockNumber },
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct DutchAuction {
+    pub seller: AccountId,
+    pub start_price: Balance,
+    pub floor_price: Balance,
+    /// Block at which the price starts to fall from `start_price`
+    pub start_block: BlockNumber,
+    /// Block at which the price reaches `floor_price`
+    pub end_block: BlockNumber,
+    pub decay: PriceDecay,
+}
+
+impl DutchAuction {
+    /// Returns the asking price at `block`, rounded down.
+    pub fn price_at(&self, block: BlockNumber) -> Balance {
+        if block <= self.start_block {
+            return self.start_price;
+        }
+        if block >= self.end_block {
+            return self.floor_price;
+        }
+        let duration = Balance::from(self.end_block - self.start_block);
+        let mut elapsed = block - self.start_block;
+        if let PriceDecay::Stepwise { step_blocks } = self.decay {
+            elapsed -= elapsed % step_blocks.max(1);
+        }
+        let elapsed = Balance::from(elapsed);
+        let range = self.start_price - self.floor_price;
+        let drop = range / duration * elapsed + range % duration * elapsed / duration;
+        self.start_price - drop
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum OfferTarget {
+    /// One specific NFT
+    Nft(CollectionId, NftId),
+    /// Any NFT of the collection
+    Collection(CollectionId),
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Offer {
+    pub buyer: AccountId,
+    pub target: OfferTarget,
+    /// Native balance held by the contract until the offer is accepted or cancelled
+    pub amount: Balance,
+    /// First block at which the offer can no longer be accepted
+    pub expires_at: BlockNumber,
+}
+
+impl OfferTarget {
+    pub fn matches(&self, collection_id: CollectionId, nft_id: NftId) -> bool {
+        match *self {
+            OfferTarget::Nft(c, n) => (c, n) == (collection_id, nft_id),
+            OfferTarget::Collection(c) => c == collection_id,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Swap {
+    pub proposer: AccountId,
+    pub counterparty: AccountId,
+    /// NFTs the proposer gives, locked in escrow from the proposal on
+    pub give: Vec<(CollectionId, NftId)>,
+    /// NFTs the counterparty gives in return
+    pub want: Vec<(CollectionId, NftId)>,
+    /// Balance the proposer paid in with the proposal
+    pub give_balance: Balance,
+    /// Balance the counterparty pays in when executing the swap
+    pub want_balance: Balance,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct MintConfig {
+    /// Price of a single NFT
+    pub price: Balance,
+    /// Most NFTs a single account may mint, if limited
+    pub max_per_account: Option<u32>,
+    /// First block of the public sale; `None` keeps `public_mint` closed, e.g. while the config
+    /// only describes the NFTs of allowlist phases or vouchers
+    pub public_start_block: Option<BlockNumber>,
+
+    /// Passed to `mint_nft` for every minted NFT
+    pub metadata: Vec<u8>,
+    pub royalty_recipient: Option<AccountId>,
+    pub royalty: Option<u32>,
+    pub transferable: bool,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct MintPhase {
+    /// Root of the merkle tree over the allowlisted `(AccountId, allowance)` pairs
+    pub merkle_root: [u8; 32],
+    /// First block of the phase
+    pub start_block: BlockNumber,
+    /// First block after the phase
+    pub end_block: BlockNumber,
+    /// Price of a single NFT during the phase
+    pub price: Balance,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct MintVoucher {
+    pub collection_id: CollectionId,
+    pub metadata: Vec<u8>,
+    pub resources: Option<Vec<ResourceTypes>>,
+    /// Account the NFT is minted to
+    pub recipient: AccountId,
+    /// Unique per voucher; a nonce can be redeemed only once
+    pub nonce: u64,
+    /// First block at which the voucher can no longer be redeemed
+    pub expiry: BlockNumber,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Reveal {
+    /// Blake2-256 hash of the SCALE-encoded `(seed, resource_hashes)`, committed before minting
+    pub provenance_hash: [u8; 32],
+    /// Seed matching the provenance hash, once published
+    pub seed: Option<Vec<u8>>,
+    /// Blake2-256 hashes of the SCALE-encoded final resources in their committed order, once
+    /// published
+    pub resource_hashes: Vec<[u8; 32]>,
+    /// Rotation derived from the seed; NFT `n` gets resource `(n + offset) % resource_hashes.len()`
+    pub offset: u32,
+}
+
+impl Reveal {
+    /// Returns the committed hash of the resource NFT `nft_id` reveals to.
+    pub fn resource_hash(&self, nft_id: NftId) -> Option<[u8; 32]> {
+        let count = self.resource_hashes.len() as u64;
+        if self.seed.is_none() || u64::from(nft_id) >= count {
+            return None;
+        }
+        let index = (u64::from(nft_id) + u64::from(self.offset)) % count;
+        self.resource_hashes.get(index as usize).copied()
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct RewardSpec {
+    /// Collection the reward is minted into
+    pub collection_id: CollectionId,
+    pub metadata: Vec<u8>,
+    pub transferable: bool,
+    pub resources: Option<Vec<ResourceTypes>>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct LootTable {
+    /// Rewards with their relative weights
+    pub entries: Vec<(u32, RewardSpec)>,
+}
+
+impl LootTable {
+    /// Picks the reward a uniformly distributed `roll` lands on, weighting every entry.
+    pub fn pick(&self, roll: u64) -> Option<&RewardSpec> {
+        let total: u64 = self
+            .entries
+            .iter()
+            .map(|(weight, _)| u64::from(*weight))
+            .sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = roll % total;
+        for (weight, reward) in self.entries.iter() {
+            let weight = u64::from(*weight);
+            if target < weight {
+                return Some(reward);
+            }
+            target -= weight;
+        }
+        None
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct LootRound {
+    /// Blake2-256 hash of the seed rolling the rewards of boxes opened in this round
+    pub commitment: [u8; 32],
+    pub seed: Option<Vec<u8>>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct OpenedBox {
+    pub opener: AccountId,
+    /// NFT the reward is minted into instead of the opener's account
+    pub destination: Option<(CollectionId, NftId)>,
+    /// Round whose seed rolls the reward
+    pub round: u32,
+    /// Block the box was opened in, mixed into the roll
+    pub opened_at: BlockNumber,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct CraftOutput {
+    /// Collection the crafted NFT is minted into
+    pub collection_id: CollectionId,
+    pub metadata: Vec<u8>,
+    pub transferable: bool,
+    pub resources: Option<Vec<ResourceTypes>>,
+    /// Key-value properties set on the crafted NFT
+    pub properties: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Recipe {
+    /// Collections the consumed NFTs come from, with how many of each are needed
+    pub inputs: Vec<(CollectionId, u32)>,
+    pub output: CraftOutput,
+    pub enabled: bool,
+}
+
+impl Recipe {
+    /// Returns whether the recipe consumes at least one NFT, every input asks for at least one
+    /// NFT and the output has metadata.
+    pub fn is_valid(&self) -> bool {
+        !self.inputs.is_empty()
+            && self.inputs.iter().all(|(_, count)| *count > 0)
+            && !self.output.metadata.is_empty()
+    }
+
+    /// Returns whether `inputs` hold exactly the NFTs the recipe consumes.
+    pub fn accepts(&self, inputs: &[(CollectionId, NftId)]) -> bool {
+        let needed = |collection_id: CollectionId| -> u64 {
+            self.inputs
+                .iter()
+                .filter(|(c, _)| *c == collection_id)
+                .map(|(_, count)| u64::from(*count))
+                .sum()
+        };
+        let total: u64 = self.inputs.iter().map(|(_, count)| u64::from(*count)).sum();
+        inputs.len() as u64 == total
+            && self.inputs.iter().all(|(collection_id, _)| {
+                inputs.iter().filter(|(c, _)| c == collection_id).count() as u64
+                    == needed(*collection_id)
+            })
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Stake {
+    /// Account that staked the NFT and gets it back when unstaking
+    pub owner: AccountId,
+    /// Collection reward index up to which rewards have been credited
+    pub reward_index: Balance,
+}
+
+impl Stake {
+    /// Rewards earned since the stake was last credited, given the collection's current index.
+    pub fn accrued(&self, reward_index: Balance) -> Balance {
+        reward_index.saturating_sub(self.reward_index)
+    }
+}
+
+/// Reward earned by one NFT staked since the collection was whitelisted, settled at
+/// `updated_at`.
+#[derive(PartialEq, Debug, Eq, Clone, Default, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct RewardIndex {
+    pub per_nft: Balance,
+    pub updated_at: BlockNumber,
+}
+
+impl RewardIndex {
+    /// Index at `now` if `rate` applied since `updated_at`.
+    pub fn at(&self, rate: Balance, now: BlockNumber) -> Balance {
+        self.per_nft
+            .saturating_add(rate.saturating_mul(Balance::from(now.saturating_sub(self.updated_at))))
+    }
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct RentalOffer {
+    /// Account that deposited the NFT and is paid for every rental
+    pub owner: AccountId,
+    pub price_per_block: Balance,
+    /// Longest rental period the owner accepts
+    pub max_blocks: BlockNumber,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Rental {
+    pub renter: AccountId,
+    /// Escrowed NFT of the renter the rented NFT is nested into
+    pub character: (CollectionId, NftId),
+    /// First block at which the rented NFT can be returned
+    pub expires_at: BlockNumber,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Proposal {
+    pub proposer: AccountId,
+    /// Collection whose NFTs carry one vote each
+    pub collection_id: CollectionId,
+    pub description: Vec<u8>,
+    /// First block at which no more votes are accepted
+    pub end_block: BlockNumber,
+    pub votes_for: u32,
+    pub votes_against: u32,
+    /// Outcome, set once the proposal is finalized after `end_block`
+    pub passed: Option<bool>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct BadgeType {
+    /// Collection badges of this type are minted into
+    pub collection_id: CollectionId,
+    pub metadata: Vec<u8>,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct Badge {
+    pub badge_type: BadgeTypeId,
+    /// Account the badge was awarded to
+    pub holder: AccountId,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct TicketedEvent {
+    /// Collection tickets are minted into
+    pub collection_id: CollectionId,
+    /// First block after the event, from which its tickets can no longer change
+    pub ends_at: BlockNumber,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub enum TierRequirement {
+    /// At least this many NFTs from the membership collections
+    Count(u32),
+    /// At least `count` NFTs from the membership collections whose property `key` equals `value`
+    Property {
+        key: Vec<u8>,
+        value: Vec<u8>,
+        count: u32,
+    },
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct MembershipTier {
+    pub name: Vec<u8>,
+    pub requirement: TierRequirement,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct MembershipConfig {
+    /// Collections whose NFTs count towards membership tiers
+    pub collections: Vec<CollectionId>,
+    /// Tiers ordered from lowest to highest
+    pub tiers: Vec<MembershipTier>,
+}
+
+impl MembershipConfig {
+    /// Returns whether a tier depends on the property `key` of NFTs in `collection_id`.
+    pub fn uses_property(&self, collection_id: CollectionId, key: &[u8]) -> bool {
+        self.collections.contains(&collection_id)
+            && self.tiers.iter().any(|tier| match &tier.requirement {
+                TierRequirement::Property { key: tier_key, .. } => tier_key == key,
+                TierRequirement::Count(_) => false,
+            })
+    }
+}
+
+/// Decodes a numeric property value.
+///
+/// Values are ASCII decimal integers with an optional leading `-`, such as `b"12"` or `b"-3"`.
+/// Anything else, including empty values, whitespace, a `+` sign, fractions and numbers outside
+/// the `i64` range, is not numeric and yields `None`.
+pub fn decode_stat(value: &[u8]) -> Option<i64> {
+    let (negative, digits) = match value.split_first() {
+        Some((b'-', digits)) => (true, digits),
+        _ => (false, value),
+    };
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let magnitude = digits.iter().try_fold(0i64, |total, digit| {
+        total.checked_mul(10)?.checked_sub(i64::from(digit - b'0'))
+    })?;
+    if negative {
+        Some(magnitude)
+    } else {
+        magnitude.checked_neg()
+    }
+}
+
+/// Encodes a numeric property value in the format `decode_stat` reads.
+pub fn encode_stat(value: i64) -> Vec<u8> {
+    format!("{}", value).into_bytes()
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct LevelUp {
+    /// Total XP at which the level is reached
+    pub xp: u64,
+    /// Resource added to the NFT on reaching the level
+    pub resource: BasicResource,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct ScheduleEntry {
+    pub start_block: BlockNumber,
+    /// First block after the entry
+    pub end_block: BlockNumber,
+    /// Resource that should be on top of the priority list during the entry
+    pub resource_id: ResourceId,
+}
+
+#[derive(PartialEq, Debug, Eq, Clone, Encode, Decode, SpreadLayout, PackedLayout)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo, StorageLayout))]
+pub struct ResourceSchedule {
+    pub entries: Vec<ScheduleEntry>,
+}
+
+impl ResourceSchedule {
+    /// Returns the resource of the first entry covering `block`.
+    pub fn active_at(&self, block: BlockNumber) -> Option<ResourceId> {
+        self.entries
+            .iter()
+            .find(|entry| entry.start_block <= block && block < entry.end_block)
+            .map(|entry| entry.resource_id)
+    }
+}